@@ -0,0 +1,2 @@
+ALTER TABLE listings
+ADD COLUMN source TEXT NOT NULL DEFAULT 'ebay_uk';
@@ -0,0 +1,24 @@
+CREATE TABLE active_listings (
+	id INTEGER NOT NULL,
+	source TEXT NOT NULL,
+	title TEXT NOT NULL,
+	price INTEGER NOT NULL,
+	link TEXT NOT NULL,
+	bids INTEGER,
+	accepts_offers BOOLEAN,
+	graded_by DEFAULT NULL REFERENCES grading_companies(id),
+	card_set_name TEXT NOT NULL,
+	card_expansion DECIMAL NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	first_seen TEXT NOT NULL,
+	last_seen TEXT NOT NULL,
+	ended_at TEXT DEFAULT NULL,
+	PRIMARY KEY (id, card_set_name, card_expansion, card_number, card_class),
+	FOREIGN KEY (card_set_name, card_expansion, card_number, card_class)
+		REFERENCES cards(set_name, expansion, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+CREATE INDEX active_listings_current ON active_listings (card_set_name, card_expansion, card_number, card_class, ended_at);
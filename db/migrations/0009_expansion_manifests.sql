@@ -0,0 +1,9 @@
+CREATE TABLE expansion_manifests (
+	set_name TEXT NOT NULL,
+	expansion DECIMAL NOT NULL,
+	expansion_name TEXT NOT NULL,
+	manifest TEXT NOT NULL,
+	enabled BOOLEAN NOT NULL DEFAULT TRUE,
+	registered_at TEXT NOT NULL,
+	PRIMARY KEY (set_name, expansion)
+);
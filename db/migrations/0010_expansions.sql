@@ -0,0 +1,195 @@
+DROP VIEW ranked_listings;
+
+CREATE TABLE expansions (
+	id INTEGER PRIMARY KEY,
+	set_name TEXT NOT NULL,
+	expansion_name TEXT NOT NULL,
+	series_number DECIMAL NOT NULL,
+	printed_total INTEGER,
+	release_date TEXT,
+	set_code TEXT,
+	manifest TEXT,
+	enabled BOOLEAN NOT NULL DEFAULT TRUE,
+	registered_at TEXT,
+	UNIQUE (set_name, series_number)
+);
+
+INSERT INTO expansions (set_name, expansion_name, series_number, printed_total, set_code, manifest, enabled, registered_at)
+SELECT
+	set_name,
+	expansion_name,
+	expansion,
+	json_extract(manifest, '$.expansion_total'),
+	json_extract(manifest, '$.expansion_abbreviation'),
+	manifest,
+	enabled,
+	registered_at
+FROM expansion_manifests;
+
+-- Expansions that were scraped before manifests were registered get filled in when their
+-- manifest is next loaded
+INSERT INTO expansions (set_name, expansion_name, series_number)
+SELECT DISTINCT
+	set_name,
+	set_name || ' ' || expansion,
+	expansion
+FROM cards
+WHERE NOT EXISTS (
+	SELECT 1
+	FROM expansions
+	WHERE expansions.set_name = cards.set_name
+	  AND expansions.series_number = cards.expansion
+);
+
+DROP TABLE expansion_manifests;
+
+ALTER TABLE cards RENAME TO cards_backup_2;
+
+CREATE TABLE cards (
+	expansion_id INTEGER NOT NULL,
+	number INTEGER NOT NULL,
+	class TEXT NOT NULL,
+	name TEXT NOT NULL,
+	rarity TEXT NOT NULL,
+	PRIMARY KEY (expansion_id, number, class),
+	FOREIGN KEY (expansion_id)
+		REFERENCES expansions(id)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+INSERT INTO cards (expansion_id, number, class, name, rarity)
+SELECT
+	expansions.id,
+	cards_backup_2.number,
+	cards_backup_2.class,
+	cards_backup_2.name,
+	cards_backup_2.rarity
+FROM cards_backup_2
+JOIN expansions
+  ON expansions.set_name = cards_backup_2.set_name
+ AND expansions.series_number = cards_backup_2.expansion;
+
+ALTER TABLE listings_cards RENAME TO listings_cards_backup;
+
+CREATE TABLE listings_cards (
+	listing_id INTEGER NOT NULL,
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	PRIMARY KEY (listing_id, card_expansion_id, card_number, card_class),
+	FOREIGN KEY (listing_id)
+		REFERENCES listings(id)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT,
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+INSERT INTO listings_cards (listing_id, card_expansion_id, card_number, card_class)
+SELECT
+	listings_cards_backup.listing_id,
+	expansions.id,
+	listings_cards_backup.card_number,
+	listings_cards_backup.card_class
+FROM listings_cards_backup
+JOIN expansions
+  ON expansions.set_name = listings_cards_backup.card_set_name
+ AND expansions.series_number = listings_cards_backup.card_expansion;
+
+DROP INDEX active_listings_current;
+ALTER TABLE active_listings RENAME TO active_listings_backup;
+
+CREATE TABLE active_listings (
+	id INTEGER NOT NULL,
+	source TEXT NOT NULL,
+	title TEXT NOT NULL,
+	price INTEGER NOT NULL,
+	link TEXT NOT NULL,
+	bids INTEGER,
+	accepts_offers BOOLEAN,
+	graded_by DEFAULT NULL REFERENCES grading_companies(id),
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	first_seen TEXT NOT NULL,
+	last_seen TEXT NOT NULL,
+	ended_at TEXT DEFAULT NULL,
+	PRIMARY KEY (id, card_expansion_id, card_number, card_class),
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+INSERT INTO active_listings
+	(id, source, title, price, link, bids, accepts_offers, graded_by, card_expansion_id, card_number, card_class, first_seen, last_seen, ended_at)
+SELECT
+	active_listings_backup.id,
+	active_listings_backup.source,
+	active_listings_backup.title,
+	active_listings_backup.price,
+	active_listings_backup.link,
+	active_listings_backup.bids,
+	active_listings_backup.accepts_offers,
+	active_listings_backup.graded_by,
+	expansions.id,
+	active_listings_backup.card_number,
+	active_listings_backup.card_class,
+	active_listings_backup.first_seen,
+	active_listings_backup.last_seen,
+	active_listings_backup.ended_at
+FROM active_listings_backup
+JOIN expansions
+  ON expansions.set_name = active_listings_backup.card_set_name
+ AND expansions.series_number = active_listings_backup.card_expansion;
+
+CREATE INDEX active_listings_current ON active_listings (card_expansion_id, card_number, card_class, ended_at);
+
+ALTER TABLE scraper_progress RENAME TO scraper_progress_backup;
+
+CREATE TABLE scraper_progress (
+	id INTEGER PRIMARY KEY CHECK (id = 1),
+	expansion_id INTEGER NOT NULL REFERENCES expansions(id),
+	number INTEGER NOT NULL,
+	class TEXT NOT NULL
+);
+
+INSERT INTO scraper_progress (id, expansion_id, number, class)
+SELECT
+	scraper_progress_backup.id,
+	expansions.id,
+	scraper_progress_backup.number,
+	scraper_progress_backup.class
+FROM scraper_progress_backup
+JOIN expansions
+  ON expansions.set_name = scraper_progress_backup.set_name
+ AND expansions.series_number = scraper_progress_backup.expansion;
+
+CREATE VIEW ranked_listings AS
+SELECT
+	listings.*,
+	expansions.id AS card_expansion_id,
+	expansions.set_name AS card_set_name,
+	expansions.series_number AS card_expansion,
+	cards.number AS card_number,
+	cards.class AS card_class,
+	cards.name AS card_name,
+	cards.rarity AS card_rarity,
+	ROW_NUMBER() OVER (
+		PARTITION BY
+			cards.expansion_id,
+			cards.number,
+			cards.class
+		ORDER BY
+			listings.date DESC
+	) AS listing_rank
+FROM
+	cards
+	JOIN expansions ON expansions.id = cards.expansion_id
+	LEFT JOIN listings_cards ON listings_cards.card_expansion_id = cards.expansion_id
+	AND listings_cards.card_number = cards.number
+	AND listings_cards.card_class = cards.class
+	LEFT JOIN listings ON listings.id = listings_cards.listing_id;
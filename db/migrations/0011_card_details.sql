@@ -0,0 +1,44 @@
+CREATE TABLE card_details (
+	expansion_id INTEGER NOT NULL,
+	number INTEGER NOT NULL,
+	image_url TEXT NOT NULL,
+	regulation_mark TEXT,
+	illustrator TEXT,
+	supertype TEXT NOT NULL,
+	stage TEXT,
+	evolves_from TEXT,
+	energy_type TEXT,
+	hp INTEGER,
+	weakness TEXT,
+	resistance TEXT,
+	retreat INTEGER,
+	paradox_type TEXT,
+	is_tera BOOLEAN,
+	ability_name TEXT,
+	ability_effect TEXT,
+	trainer_type TEXT,
+	text TEXT,
+	PRIMARY KEY (expansion_id, number),
+	FOREIGN KEY (expansion_id)
+		REFERENCES expansions(id)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+CREATE INDEX card_details_illustrator ON card_details (illustrator);
+CREATE INDEX card_details_energy_type ON card_details (energy_type);
+
+CREATE TABLE card_attacks (
+	expansion_id INTEGER NOT NULL,
+	number INTEGER NOT NULL,
+	position INTEGER NOT NULL,
+	name TEXT NOT NULL,
+	cost TEXT NOT NULL,
+	effect TEXT,
+	damage TEXT,
+	PRIMARY KEY (expansion_id, number, position),
+	FOREIGN KEY (expansion_id, number)
+		REFERENCES card_details(expansion_id, number)
+		ON DELETE CASCADE
+		ON UPDATE RESTRICT
+);
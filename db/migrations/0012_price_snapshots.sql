@@ -0,0 +1,18 @@
+CREATE TABLE price_snapshots (
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	date TEXT NOT NULL,
+	graded BOOLEAN NOT NULL,
+	sample_count INTEGER NOT NULL,
+	outlier_count INTEGER NOT NULL,
+	median INTEGER NOT NULL,
+	trimmed_mean INTEGER NOT NULL,
+	min INTEGER NOT NULL,
+	max INTEGER NOT NULL,
+	PRIMARY KEY (card_expansion_id, card_number, card_class, date, graded),
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
@@ -0,0 +1,8 @@
+ALTER TABLE listings ADD COLUMN grade REAL;
+ALTER TABLE listings ADD COLUMN grade_qualifier TEXT;
+
+ALTER TABLE active_listings ADD COLUMN grade REAL;
+ALTER TABLE active_listings ADD COLUMN grade_qualifier TEXT;
+
+-- INFO: Existing companies were matched anywhere in the title, so "Ace Spec" cards were stored as
+-- ACE slabs. POST /admin/listings/regrade re-derives them with the new parser.
@@ -0,0 +1,3 @@
+-- Comma separated names of the title rules that flagged a listing for review
+ALTER TABLE listings ADD COLUMN flagged_by TEXT;
+ALTER TABLE active_listings ADD COLUMN flagged_by TEXT;
@@ -0,0 +1,35 @@
+CREATE TABLE rejected_listings (
+	id INTEGER PRIMARY KEY,
+	source TEXT NOT NULL,
+	-- NULL when the listing couldn't be fully parsed
+	listing_id INTEGER,
+	title TEXT NOT NULL,
+	link TEXT NOT NULL,
+	date TEXT,
+	price INTEGER,
+	raw_price TEXT,
+	bids INTEGER,
+	accepts_offers BOOLEAN,
+	offer_was_accepted BOOLEAN,
+	graded_by INTEGER REFERENCES grading_companies(id),
+	grade REAL,
+	grade_qualifier TEXT,
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	-- title_rule | invalid_price
+	reason TEXT NOT NULL,
+	-- Name of the title rule when reason = 'title_rule'
+	rule TEXT,
+	-- pending | accepted | confirmed
+	status TEXT NOT NULL DEFAULT 'pending',
+	rejected_at TEXT NOT NULL,
+	reviewed_at TEXT,
+	UNIQUE (source, link, card_expansion_id, card_number, card_class),
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+CREATE INDEX rejected_listings_review ON rejected_listings (status, rejected_at);
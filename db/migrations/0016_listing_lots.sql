@@ -0,0 +1,41 @@
+-- single | quantity | mixed
+ALTER TABLE listings ADD COLUMN lot_kind TEXT NOT NULL DEFAULT 'single';
+-- NULL for mixed lots
+ALTER TABLE listings ADD COLUMN quantity INTEGER DEFAULT 1;
+-- Price of one card, NULL for mixed lots
+ALTER TABLE listings ADD COLUMN unit_price INTEGER;
+
+UPDATE listings SET unit_price = price;
+
+-- Mixed lots can't be priced per card so they're left out of the ranked listings used for
+-- statistics
+DROP VIEW ranked_listings;
+
+CREATE VIEW ranked_listings AS
+SELECT
+	listings.*,
+	expansions.id AS card_expansion_id,
+	expansions.set_name AS card_set_name,
+	CAST(expansions.series_number AS REAL) AS card_expansion,
+	cards.number AS card_number,
+	cards.class AS card_class,
+	cards.name AS card_name,
+	cards.rarity AS card_rarity,
+	ROW_NUMBER() OVER (
+		PARTITION BY
+			cards.expansion_id,
+			cards.number,
+			cards.class
+		ORDER BY
+			listings.date DESC
+	) AS listing_rank
+FROM
+	cards
+	JOIN expansions ON expansions.id = cards.expansion_id
+	LEFT JOIN listings_cards ON listings_cards.card_expansion_id = cards.expansion_id
+	AND listings_cards.card_number = cards.number
+	AND listings_cards.card_class = cards.class
+	LEFT JOIN listings ON listings.id = listings_cards.listing_id
+WHERE
+	listings.id IS NULL
+	OR listings.lot_kind != 'mixed';
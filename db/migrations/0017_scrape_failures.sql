@@ -0,0 +1,18 @@
+CREATE TABLE scrape_failures (
+	id INTEGER PRIMARY KEY,
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	-- 1 for the first attempt at the card in a cycle
+	attempt INTEGER NOT NULL,
+	error TEXT NOT NULL,
+	-- Whether the card was skipped for the rest of the cycle after this failure
+	gave_up BOOLEAN NOT NULL,
+	failed_at TEXT NOT NULL,
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+CREATE INDEX scrape_failures_failed_at ON scrape_failures (failed_at);
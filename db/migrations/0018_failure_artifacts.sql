@@ -0,0 +1,11 @@
+CREATE TABLE failure_artifacts (
+	id INTEGER PRIMARY KEY,
+	scrape_failure_id INTEGER NOT NULL UNIQUE REFERENCES scrape_failures(id) ON DELETE CASCADE,
+	-- Directory holding the bundle, relative to the working directory
+	directory TEXT NOT NULL,
+	-- NULL when the WebDriver couldn't report which page it was on
+	url TEXT,
+	has_screenshot BOOLEAN NOT NULL,
+	has_page_source BOOLEAN NOT NULL,
+	captured_at TEXT NOT NULL
+);
@@ -0,0 +1,22 @@
+-- The schedule replaces the single cursor that cycles resumed from
+DROP TABLE scraper_progress;
+
+CREATE TABLE scrape_schedule (
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	interval_seconds INTEGER NOT NULL,
+	priority INTEGER NOT NULL,
+	-- Name of the schedule rule the interval came from
+	rule TEXT NOT NULL,
+	-- Includes attempts that failed, so that a broken card doesn't hold up the rest
+	last_attempt_at TEXT,
+	next_due_at TEXT NOT NULL,
+	PRIMARY KEY (card_expansion_id, card_number, card_class),
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE CASCADE
+		ON UPDATE RESTRICT
+);
+
+CREATE INDEX scrape_schedule_next_due_at ON scrape_schedule (next_due_at);
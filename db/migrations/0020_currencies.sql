@@ -0,0 +1,20 @@
+-- Every listing was scraped from eBay UK before currencies were stored
+ALTER TABLE listings ADD COLUMN currency TEXT NOT NULL DEFAULT 'GBP';
+
+ALTER TABLE active_listings ADD COLUMN currency TEXT NOT NULL DEFAULT 'GBP';
+
+-- Only set when the price could be parsed
+ALTER TABLE rejected_listings ADD COLUMN currency TEXT;
+UPDATE rejected_listings SET currency = 'GBP' WHERE price IS NOT NULL;
+
+-- The reporting currency that listings were converted to
+ALTER TABLE price_snapshots ADD COLUMN currency TEXT NOT NULL DEFAULT 'GBP';
+
+CREATE TABLE fx_rates (
+	date TEXT NOT NULL,
+	base TEXT NOT NULL,
+	quote TEXT NOT NULL,
+	-- Decimal string of units of quote bought by one unit of base, kept exact
+	rate TEXT NOT NULL,
+	PRIMARY KEY (date, base, quote)
+);
@@ -0,0 +1,41 @@
+-- Postage in minor units of currency, 0 when free and NULL when the listing didn't say
+ALTER TABLE listings ADD COLUMN postage INTEGER;
+-- Price of one card including its share of postage, NULL for mixed lots or unknown postage
+ALTER TABLE listings ADD COLUMN delivered_unit_price INTEGER;
+
+ALTER TABLE active_listings ADD COLUMN postage INTEGER;
+
+ALTER TABLE rejected_listings ADD COLUMN postage INTEGER;
+
+-- Snapshots are taken of both item and delivered prices, which needs basis in the primary key
+CREATE TABLE price_snapshots_new (
+	card_expansion_id INTEGER NOT NULL,
+	card_number INTEGER NOT NULL,
+	card_class TEXT NOT NULL,
+	date TEXT NOT NULL,
+	graded BOOLEAN NOT NULL,
+	-- item | delivered
+	basis TEXT NOT NULL,
+	sample_count INTEGER NOT NULL,
+	outlier_count INTEGER NOT NULL,
+	median INTEGER NOT NULL,
+	trimmed_mean INTEGER NOT NULL,
+	min INTEGER NOT NULL,
+	max INTEGER NOT NULL,
+	currency TEXT NOT NULL,
+	PRIMARY KEY (card_expansion_id, card_number, card_class, date, graded, basis),
+	FOREIGN KEY (card_expansion_id, card_number, card_class)
+		REFERENCES cards(expansion_id, number, class)
+		ON DELETE RESTRICT
+		ON UPDATE RESTRICT
+);
+
+INSERT INTO price_snapshots_new
+	(card_expansion_id, card_number, card_class, date, graded, basis, sample_count, outlier_count, median, trimmed_mean, min, max, currency)
+SELECT
+	card_expansion_id, card_number, card_class, date, graded, 'item', sample_count, outlier_count, median, trimmed_mean, min, max, currency
+FROM price_snapshots;
+
+DROP TABLE price_snapshots;
+
+ALTER TABLE price_snapshots_new RENAME TO price_snapshots;
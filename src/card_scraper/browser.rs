@@ -0,0 +1,56 @@
+use thirtyfour::{error::WebDriverResult, Capabilities, WebDriver};
+
+use crate::monitoring;
+
+fn count_error<T>(call: &'static str) -> impl FnOnce(&T) {
+    move |_| metrics::counter!(monitoring::WEBDRIVER_ERRORS, "call" => call).increment(1)
+}
+
+/// The WebDriver session used for a scrape cycle.
+///
+/// Every call the scraper makes to the browser goes through here so that each one gets its own
+/// span, nested under the card that it was made for.
+#[derive(Debug)]
+pub struct Browser {
+    driver: WebDriver,
+}
+
+impl Browser {
+    #[tracing::instrument(name = "webdriver.connect", skip(capabilities), err)]
+    pub async fn connect(url: &str, capabilities: Capabilities) -> WebDriverResult<Self> {
+        WebDriver::new(url, capabilities)
+            .await
+            .map(|driver| Self { driver })
+            .inspect_err(count_error("connect"))
+    }
+
+    #[tracing::instrument(name = "webdriver.goto", level = "debug", skip(self), err)]
+    pub async fn goto(&self, url: &str) -> WebDriverResult<()> {
+        self.driver.goto(url).await.inspect_err(count_error("goto"))
+    }
+
+    #[tracing::instrument(name = "webdriver.source", level = "debug", skip(self), err)]
+    pub async fn source(&self) -> WebDriverResult<String> {
+        self.driver
+            .source()
+            .await
+            .inspect_err(count_error("source"))
+    }
+
+    #[tracing::instrument(name = "webdriver.current_url", level = "debug", skip(self), err)]
+    pub async fn current_url(&self) -> WebDriverResult<String> {
+        self.driver
+            .current_url()
+            .await
+            .map(|x| x.to_string())
+            .inspect_err(count_error("current_url"))
+    }
+
+    #[tracing::instrument(name = "webdriver.screenshot", skip(self), err)]
+    pub async fn screenshot(&self, path: &std::path::Path) -> WebDriverResult<()> {
+        self.driver
+            .screenshot(path)
+            .await
+            .inspect_err(count_error("screenshot"))
+    }
+}
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum EnergyType {
+    Grass,
+    Fire,
+    Water,
+    Lightning,
+    Psychic,
+    Fighting,
+    Dark,
+    Metal,
+    Dragon,
+    Colourless,
+}
+
+impl std::fmt::Display for EnergyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Grass => "Grass",
+                Self::Fire => "Fire",
+                Self::Water => "Water",
+                Self::Lightning => "Lightning",
+                Self::Psychic => "Psychic",
+                Self::Fighting => "Fighting",
+                Self::Dark => "Dark",
+                Self::Metal => "Metal",
+                Self::Dragon => "Dragon",
+                Self::Colourless => "Colourless",
+            }
+        )
+    }
+}
+
+/// The `card_varient` object of a manifest card.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum CardDetails {
+    Pokemon(PokemonDetails),
+    Trainer(TrainerDetails),
+    Energy(EnergyCard),
+}
+
+impl CardDetails {
+    pub fn supertype(&self) -> &'static str {
+        match self {
+            Self::Pokemon(_) => "Pokemon",
+            Self::Trainer(_) => "Trainer",
+            Self::Energy(_) => "Energy",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PokemonDetails {
+    pub ability: Option<Ability>,
+    pub attacks: Vec<Attack>,
+    pub stage: Stage,
+    pub energy: Option<EnergyType>,
+    pub hp: u32,
+    pub weakness: Option<EnergyType>,
+    pub resistance: Option<EnergyType>,
+    pub retreat: u8,
+    pub paradox_type: Option<ParadoxType>,
+    pub is_tera: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Basic,
+    /// Evolves from the named Pokémon.
+    Stage1(String),
+    /// Evolves from the named Pokémon.
+    Stage2(String),
+}
+
+impl Stage {
+    pub fn evolves_from(&self) -> Option<&str> {
+        match self {
+            Self::Basic => None,
+            Self::Stage1(x) | Self::Stage2(x) => Some(x),
+        }
+    }
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Basic => "Basic",
+                Self::Stage1(_) => "Stage 1",
+                Self::Stage2(_) => "Stage 2",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ParadoxType {
+    Ancient,
+    Future,
+}
+
+impl std::fmt::Display for ParadoxType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Ancient => "Ancient",
+                Self::Future => "Future",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Ability {
+    pub name: String,
+    pub effect: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Attack {
+    #[serde(rename = "types")]
+    pub cost: Vec<EnergyType>,
+    pub name: String,
+    pub effect: Option<String>,
+    pub damage: Option<Damage>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Damage {
+    Exact(u32),
+    /// Printed as "30+".
+    Plus(u32),
+    /// Printed as "30-".
+    Minus(u32),
+    /// Printed as "30×".
+    Multiple(u32),
+}
+
+impl std::fmt::Display for Damage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(x) => write!(f, "{x}"),
+            Self::Plus(x) => write!(f, "{x}+"),
+            Self::Minus(x) => write!(f, "{x}-"),
+            Self::Multiple(x) => write!(f, "{x}×"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrainerDetails {
+    pub trainer_type: TrainerType,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TrainerType {
+    Supporter,
+    Stadium,
+    Item {
+        /// Fossils can be played as Pokémon with an ability and HP.
+        ability: Option<Ability>,
+        hp: Option<u32>,
+    },
+    Tool {
+        attack: Option<Attack>,
+    },
+}
+
+impl std::fmt::Display for TrainerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Supporter => "Supporter",
+                Self::Stadium => "Stadium",
+                Self::Item { .. } => "Item",
+                Self::Tool { .. } => "Tool",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyCard {
+    Basic,
+    Special { text: String },
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn it_deserialises_a_pokemon() {
+        let Ok(CardDetails::Pokemon(pokemon)) = serde_json::from_str::<CardDetails>(
+            r#"{"pokemon":{"ability":null,"attacks":[{"types":["Colourless"],"name":"Feelin' Fine","effect":"Draw a card.","damage":null},{"types":["Grass"],"name":"Stampede","effect":null,"damage":{"exact":10}}],"stage":"basic","energy":"Grass","hp":50,"weakness":"Fire","resistance":null,"retreat":1,"paradox_type":null,"is_tera":false}}"#,
+        ) else {
+            panic!("Failed to deserialise pokemon");
+        };
+
+        assert_eq!(pokemon.stage, Stage::Basic);
+        assert_eq!(pokemon.energy, Some(EnergyType::Grass));
+        assert_eq!(pokemon.hp, 50);
+        assert_eq!(pokemon.attacks.len(), 2);
+        assert_eq!(pokemon.attacks[1].damage, Some(Damage::Exact(10)));
+    }
+
+    #[test]
+    fn it_deserialises_an_evolution() {
+        let Ok(stage) = serde_json::from_str::<Stage>(r#"{"stage1": "Lickitung"}"#) else {
+            panic!("Failed to deserialise stage");
+        };
+
+        assert_eq!(stage.to_string(), "Stage 1");
+        assert_eq!(stage.evolves_from(), Some("Lickitung"));
+    }
+
+    #[test]
+    fn it_deserialises_trainers() {
+        let Ok(CardDetails::Trainer(trainer)) = serde_json::from_str::<CardDetails>(
+            r#"{"trainer":{"trainer_type":{"item":{"ability":{"name":"Primal Root","effect":"Attacks cost more."},"hp":60}},"text":null}}"#,
+        ) else {
+            panic!("Failed to deserialise trainer");
+        };
+
+        assert!(matches!(
+            trainer.trainer_type,
+            TrainerType::Item {
+                ability: Some(_),
+                hp: Some(60)
+            }
+        ));
+
+        let Ok(CardDetails::Trainer(trainer)) = serde_json::from_str::<CardDetails>(
+            r#"{"trainer":{"trainer_type":"supporter","text":"Draw 3 cards."}}"#,
+        ) else {
+            panic!("Failed to deserialise trainer");
+        };
+
+        assert_eq!(trainer.trainer_type.to_string(), "Supporter");
+    }
+
+    #[test]
+    fn it_deserialises_energy() {
+        assert!(matches!(
+            serde_json::from_str::<CardDetails>(r#"{"energy":"basic"}"#),
+            Ok(CardDetails::Energy(EnergyCard::Basic))
+        ));
+        assert!(matches!(
+            serde_json::from_str::<CardDetails>(
+                r#"{"energy":{"special":{"text":"Provides [C]."}}}"#
+            ),
+            Ok(CardDetails::Energy(EnergyCard::Special { .. }))
+        ));
+    }
+
+    #[test]
+    fn it_displays_damage_modifiers() {
+        assert_eq!(Damage::Exact(30).to_string(), "30");
+        assert_eq!(Damage::Plus(30).to_string(), "30+");
+        assert_eq!(Damage::Multiple(20).to_string(), "20×");
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Notify};
+
+use super::Pokemon;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScraperState {
+    #[default]
+    Idle,
+    Scraping,
+    /// Waiting to be resumed, either between cards or before the next pass over the schedule.
+    Paused,
+}
+
+/// The card that is being scraped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CurrentCard {
+    pub expansion_id: u32,
+    pub set_name: String,
+    pub expansion_name: String,
+    pub number: u32,
+    pub class: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastError {
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+/// A scrape of one expansion, or one card of it, outside of the schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeRequest {
+    pub expansion_id: u32,
+    /// Every card in the expansion when not set.
+    pub number: Option<u32>,
+    /// Every class of the card when not set.
+    pub class: Option<String>,
+}
+
+impl ScrapeRequest {
+    /// Whether a card with a single class is part of the requested scrape.
+    pub fn matches(&self, card: &Pokemon) -> bool {
+        self.number.is_none_or(|x| x as usize == card.number)
+            && self
+                .class
+                .as_ref()
+                .is_none_or(|x| card.class.iter().any(|class| class.to_string() == *x))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScraperStatus {
+    pub state: ScraperState,
+    pub paused: bool,
+    pub current: Option<CurrentCard>,
+    /// Cards scraped so far in this batch of due cards, and how many the batch has in total.
+    pub cards_scraped: usize,
+    pub cards_total: usize,
+    pub last_error: Option<LastError>,
+    pub next_run: Option<DateTime<Utc>>,
+    pub queued: Vec<ScrapeRequest>,
+}
+
+/// State shared between the scraper and the admin API.
+///
+/// The admin API only ever asks for something to happen; the scraper acts on it between cards
+/// so that a card's listings are never half stored.
+#[derive(Debug)]
+pub struct ScraperControl {
+    status: Mutex<ScraperStatus>,
+    paused: watch::Sender<bool>,
+    wake: Notify,
+    triggered: AtomicBool,
+    queue: Mutex<VecDeque<ScrapeRequest>>,
+}
+
+impl Default for ScraperControl {
+    fn default() -> Self {
+        Self {
+            status: Mutex::default(),
+            paused: watch::Sender::new(false),
+            wake: Notify::new(),
+            triggered: AtomicBool::new(false),
+            queue: Mutex::default(),
+        }
+    }
+}
+
+impl ScraperControl {
+    pub fn status(&self) -> ScraperStatus {
+        ScraperStatus {
+            paused: *self.paused.borrow(),
+            queued: self.queue.lock().unwrap().iter().cloned().collect(),
+            ..self.status.lock().unwrap().clone()
+        }
+    }
+
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Checks the schedule for due cards without waiting for the sleep to finish.
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::Relaxed);
+        self.wake.notify_one();
+    }
+
+    pub fn enqueue(&self, request: ScrapeRequest) {
+        self.queue.lock().unwrap().push_back(request);
+        self.wake.notify_one();
+    }
+
+    pub fn has_queued(&self) -> bool {
+        !self.queue.lock().unwrap().is_empty()
+    }
+
+    pub fn next_queued(&self) -> Option<ScrapeRequest> {
+        self.queue.lock().unwrap().pop_front()
+    }
+
+    /// Waits until a pass is triggered or a scrape is queued, returning whether it was a trigger.
+    pub async fn woken(&self) -> bool {
+        self.wake.notified().await;
+        self.triggered.swap(false, Ordering::Relaxed)
+    }
+
+    pub async fn wait_while_paused(&self) {
+        let mut paused = self.paused.subscribe();
+        if !*paused.borrow_and_update() {
+            return;
+        }
+
+        let state = self.update(|x| std::mem::replace(&mut x.state, ScraperState::Paused));
+        tracing::info!("Scraper paused");
+        // INFO: The sender lives as long as `self`, so this can't fail
+        let _ = paused.wait_for(|x| !x).await;
+        tracing::info!("Scraper resumed");
+        self.update(|x| x.state = state);
+    }
+
+    pub fn start_cycle(&self, cards_total: usize) {
+        self.update(|x| {
+            x.state = ScraperState::Scraping;
+            x.cards_scraped = 0;
+            x.cards_total = cards_total;
+            x.next_run = None;
+        });
+        // INFO: A trigger during the pass has already been acted on
+        self.triggered.store(false, Ordering::Relaxed);
+    }
+
+    pub fn sleep_until(&self, next_run: DateTime<Utc>) {
+        self.update(|x| {
+            x.state = ScraperState::Idle;
+            x.current = None;
+            x.next_run = Some(next_run);
+        });
+    }
+
+    pub fn set_current(&self, current: Option<CurrentCard>) {
+        self.update(|x| x.current = current);
+    }
+
+    pub fn card_scraped(&self) {
+        self.update(|x| x.cards_scraped += 1);
+    }
+
+    pub fn set_error(&self, message: impl Into<String>) {
+        self.update(|x| {
+            x.last_error = Some(LastError {
+                message: message.into(),
+                at: Utc::now(),
+            })
+        });
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut ScraperStatus) -> T) -> T {
+        f(&mut self.status.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(number: u32) -> ScrapeRequest {
+        ScrapeRequest {
+            expansion_id: 1,
+            number: Some(number),
+            class: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_wakes_on_triggers_and_queued_scrapes() {
+        let control = ScraperControl::default();
+
+        control.trigger();
+        assert!(control.woken().await);
+
+        control.enqueue(request(1));
+        control.enqueue(request(2));
+        assert!(!control.woken().await);
+        assert_eq!(control.status().queued, vec![request(1), request(2)]);
+        assert_eq!(control.next_queued(), Some(request(1)));
+        assert_eq!(control.next_queued(), Some(request(2)));
+        assert_eq!(control.next_queued(), None);
+    }
+
+    #[tokio::test]
+    async fn it_waits_until_resumed() {
+        let control = std::sync::Arc::new(ScraperControl::default());
+        control.start_cycle(10);
+        control.pause();
+
+        let waiting = tokio::spawn({
+            let control = control.clone();
+            async move { control.wait_while_paused().await }
+        });
+        tokio::task::yield_now().await;
+        while control.status().state != ScraperState::Paused {
+            tokio::task::yield_now().await;
+        }
+
+        control.resume();
+        let Ok(_) = waiting.await else {
+            panic!("Failed to wait while paused");
+        };
+        assert_eq!(control.status().state, ScraperState::Scraping);
+        assert!(!control.status().paused);
+    }
+}
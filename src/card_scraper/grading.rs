@@ -0,0 +1,331 @@
+use regex::{Regex, RegexBuilder};
+use sqlx::sqlite::Sqlite;
+
+/// Label printed on a slab on top of its numeric grade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    /// BGS 10 with four 10 subgrades.
+    BlackLabel,
+    /// BGS 10 without four 10 subgrades.
+    GoldLabel,
+    /// BGS and CGC's "Pristine 10".
+    Pristine,
+    /// CGC's "Perfect 10".
+    Perfect,
+}
+
+impl std::fmt::Display for Qualifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::BlackLabel => "Black Label",
+                Self::GoldLabel => "Gold Label",
+                Self::Pristine => "Pristine",
+                Self::Perfect => "Perfect",
+            }
+        )
+    }
+}
+
+/// A grading company's assessment of a slabbed card, as described by a listing title.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grade {
+    pub company_id: u32,
+    pub company: String,
+    /// Numeric grade from 1 to 10 in steps of 0.5, if the title states it.
+    pub grade: Option<f32>,
+    pub qualifier: Option<Qualifier>,
+}
+
+impl std::fmt::Display for Grade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.company)?;
+        if let Some(grade) = self.grade {
+            write!(f, " {grade}")?;
+        }
+        if let Some(qualifier) = self.qualifier {
+            write!(f, " {qualifier}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Extracts grades from listing titles using the companies in `grading_companies`.
+#[derive(Debug, Clone)]
+pub struct GradeParser {
+    companies: Vec<(u32, String)>,
+    grade: Regex,
+    not_a_grade: Regex,
+    qualifiers: Vec<(Qualifier, Regex)>,
+}
+
+fn case_insensitive(pattern: &str) -> Regex {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .expect("Grading patterns should be valid")
+}
+
+impl GradeParser {
+    pub fn new(companies: Vec<(u32, String)>) -> Self {
+        let initials = companies
+            .iter()
+            .map(|(_, initials)| regex::escape(initials))
+            .collect::<Vec<_>>()
+            .join("|");
+
+        Self {
+            // INFO: The words between the company and the grade cover titles like
+            // "PSA Gem Mint 10" and "CGC Graded 9.5". The grade is optional so that slabs without
+            // a stated grade still count as graded.
+            grade: case_insensitive(&format!(
+                r"\b({initials})(?:\s*(?:graded|grade|grading|gem\s+mint|gem|mint|pristine|perfect|[:#-]))*\s*(10|[1-9](?:\.5)?)?\b"
+            )),
+            // INFO: Ace Spec is a rarity, not an ACE grade
+            not_a_grade: case_insensitive(r"^\s*spec\b"),
+            qualifiers: vec![
+                (
+                    Qualifier::BlackLabel,
+                    case_insensitive(r"\bblack\s+label\b"),
+                ),
+                (Qualifier::GoldLabel, case_insensitive(r"\bgold\s+label\b")),
+                (Qualifier::Pristine, case_insensitive(r"\bpristine\b")),
+                (Qualifier::Perfect, case_insensitive(r"\bperfect\s*10\b")),
+            ],
+            companies,
+        }
+    }
+
+    pub async fn load(pool: &sqlx::Pool<Sqlite>) -> Result<Self, String> {
+        sqlx::query_as::<_, (u32, String)>("SELECT id, initials FROM grading_companies")
+            .fetch_all(pool)
+            .await
+            .map(Self::new)
+            .map_err(|e| format!("Failed to get grading companies: {e}"))
+    }
+
+    /// Returns the grade of a slab, preferring a company followed by a grade over a bare company.
+    ///
+    /// A company without a grade must be written in capitals, as lowercase initials such as
+    /// "ace" are too often ordinary words.
+    pub fn parse(&self, title: &str) -> Option<Grade> {
+        let (company, grade) = self
+            .grade
+            .captures_iter(title)
+            .filter_map(|captures| {
+                let company = captures.get(1)?;
+                if self.not_a_grade.is_match(&title[company.end()..]) {
+                    return None;
+                }
+
+                let grade = captures.get(2).and_then(|x| x.as_str().parse::<f32>().ok());
+                if grade.is_none() && company.as_str() != company.as_str().to_uppercase() {
+                    return None;
+                }
+
+                Some((company.as_str(), grade))
+            })
+            .reduce(|first, x| match (first.1, x.1) {
+                (None, Some(_)) => x,
+                _ => first,
+            })?;
+
+        let (company_id, company) = self
+            .companies
+            .iter()
+            .find(|(_, initials)| initials.eq_ignore_ascii_case(company))?;
+
+        Some(Grade {
+            company_id: *company_id,
+            company: company.clone(),
+            grade,
+            qualifier: self
+                .qualifiers
+                .iter()
+                .find(|(_, pattern)| pattern.is_match(title))
+                .map(|(qualifier, _)| *qualifier),
+        })
+    }
+}
+
+/// Re-derives the grade of every stored listing from its title, returning how many changed.
+pub async fn regrade_listings(
+    pool: &sqlx::Pool<Sqlite>,
+    parser: &GradeParser,
+) -> Result<u64, String> {
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error creating transaction: {e}"))?;
+    let mut changed = 0;
+
+    for table in ["listings", "active_listings"] {
+        let listings =
+            sqlx::query_as::<_, (i64, String)>(&format!("SELECT DISTINCT id, title FROM {table}"))
+                .fetch_all(&mut *txn)
+                .await
+                .map_err(|e| format!("Failed to get {table}: {e}"))?;
+
+        for (id, title) in listings {
+            let grade = parser.parse(&title);
+
+            changed += sqlx::query(&format!(
+                "
+                UPDATE {table}
+                SET graded_by = ?, grade = ?, grade_qualifier = ?
+                WHERE id = ?
+                  AND (graded_by, grade, grade_qualifier) IS NOT (?, ?, ?)
+                "
+            ))
+            .bind(grade.as_ref().map(|x| x.company_id))
+            .bind(grade.as_ref().and_then(|x| x.grade))
+            .bind(
+                grade
+                    .as_ref()
+                    .and_then(|x| x.qualifier)
+                    .map(|x| x.to_string()),
+            )
+            .bind(id)
+            .bind(grade.as_ref().map(|x| x.company_id))
+            .bind(grade.as_ref().and_then(|x| x.grade))
+            .bind(
+                grade
+                    .as_ref()
+                    .and_then(|x| x.qualifier)
+                    .map(|x| x.to_string()),
+            )
+            .execute(&mut *txn)
+            .await
+            .map_err(|e| format!("Failed to update {table}: {e}"))?
+            .rows_affected();
+        }
+    }
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit grades: {e}"))?;
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parser() -> GradeParser {
+        GradeParser::new(
+            ["PSA", "ACE", "CGC", "UGC", "BGS", "SGC", "GMA"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, x)| (i as u32 + 1, x.to_string()))
+                .collect(),
+        )
+    }
+
+    fn grade(title: &str) -> Option<String> {
+        parser().parse(title).map(|x| x.to_string())
+    }
+
+    #[test]
+    fn it_parses_graded_titles() {
+        let cases = [
+            ("Charizard ex 199/165 PSA 10 Pokemon 151 SIR", "PSA 10"),
+            ("PSA10 Pikachu ex 238/191 Surging Sparks", "PSA 10"),
+            (
+                "Pokemon Umbreon ex 161/131 Prismatic Evolutions PSA Gem Mint 10",
+                "PSA 10",
+            ),
+            (
+                "Iono's Bellibolt ex 253/159 CGC 9.5 Journey Together",
+                "CGC 9.5",
+            ),
+            ("Gardevoir ex 245/198 - BGS 9.5 Gem Mint", "BGS 9.5"),
+            ("Mew ex 232/091 Paldean Fates ACE 10 Graded", "ACE 10"),
+            (
+                "Greninja ex 214/167 Twilight Masquerade Graded: SGC 8.5",
+                "SGC 8.5",
+            ),
+            ("Pokemon Lugia V Alt Art 186/195 PSA 7 NM", "PSA 7"),
+            ("Sylveon ex 156/131 Prismatic Evolutions ugc 9", "UGC 9"),
+            ("Eevee 173/165 PSA Graded 151 Illustration Rare", "PSA"),
+        ];
+
+        for (title, expected) in cases {
+            assert_eq!(grade(title).as_deref(), Some(expected), "{title}");
+        }
+    }
+
+    #[test]
+    fn it_parses_qualifiers() {
+        assert_eq!(
+            grade("Pikachu ex 219/191 BGS 10 Black Label Surging Sparks").as_deref(),
+            Some("BGS 10 Black Label")
+        );
+        assert_eq!(
+            grade("Roaring Moon ex 251/182 BGS 10 Pristine Paradox Rift").as_deref(),
+            Some("BGS 10 Pristine")
+        );
+        assert_eq!(
+            grade("Miraidon ex 244/198 CGC Pristine 10").as_deref(),
+            Some("CGC 10 Pristine")
+        );
+        assert_eq!(
+            grade("Magikarp 203/193 CGC Perfect 10 Paldea Evolved").as_deref(),
+            Some("CGC 10 Perfect")
+        );
+        assert_eq!(
+            grade("Dragapult ex 200/167 BGS 10 Gold Label").as_deref(),
+            Some("BGS 10 Gold Label")
+        );
+    }
+
+    #[test]
+    fn it_ignores_ace_spec_cards() {
+        let titles = [
+            "Prime Catcher 157/162 Ace Spec Temporal Forces",
+            "Master Ball 153/167 ACE SPEC Twilight Masquerade Pokemon",
+            "Pokemon Unfair Stamp ace spec 165/167 Near Mint",
+        ];
+
+        for title in titles {
+            assert_eq!(grade(title), None, "{title}");
+        }
+
+        assert_eq!(
+            grade("Neutralization Zone 224/191 Ace Spec ACE 10").as_deref(),
+            Some("ACE 10")
+        );
+    }
+
+    #[test]
+    fn it_matches_whole_words() {
+        let titles = [
+            "Dogma ex Pokemon Card",
+            "Pokemon Space Time Smackdown Palace",
+            "Pokemon Pace Setter Near Mint",
+            "Pokemon Mint Condition Pikachu 063/198 psa ready",
+            "Ace Trainer Pokemon Card",
+        ];
+
+        for title in titles {
+            assert_eq!(grade(title), None, "{title}");
+        }
+    }
+
+    #[test]
+    fn it_prefers_a_stated_grade() {
+        assert_eq!(
+            grade("PSA Certified Pikachu 027/198 - PSA 9 Mint").as_deref(),
+            Some("PSA 9")
+        );
+    }
+
+    #[test]
+    fn it_ignores_numbers_that_arent_grades() {
+        assert_eq!(grade("Pikachu PSA 025/165").as_deref(), Some("PSA"),);
+        assert_eq!(grade("CGC 100 Pokemon Bulk").as_deref(), Some("CGC"));
+    }
+}
@@ -0,0 +1,205 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::currency::{Money, Rounding};
+
+/// How many cards a listing sells, as described by its title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lot {
+    Single,
+    /// Several copies of the same card.
+    Quantity(u32),
+    /// Several different cards, or a listing to choose one from.
+    Mixed,
+}
+
+impl Lot {
+    /// Kind stored in `listings.lot_kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Single => "single",
+            Self::Quantity(_) => "quantity",
+            Self::Mixed => "mixed",
+        }
+    }
+
+    pub fn quantity(&self) -> Option<u32> {
+        match self {
+            Self::Single => Some(1),
+            Self::Quantity(x) => Some(*x),
+            Self::Mixed => None,
+        }
+    }
+
+    /// Price of a single card, rounded half up to the nearest minor unit, or `None` for mixed
+    /// lots.
+    pub fn unit_price(&self, price: Money) -> Option<Money> {
+        self.quantity()
+            .and_then(|quantity| price.div_rounded(quantity.into(), Rounding::HalfUp).ok())
+    }
+
+    /// Price of a single card including its share of postage, or `None` for mixed lots and
+    /// listings that didn't say what postage cost.
+    pub fn delivered_unit_price(&self, price: Money, postage: Option<Money>) -> Option<Money> {
+        self.unit_price((price + postage?).ok()?)
+    }
+}
+
+/// Classifies listing titles as single cards, quantities of one card or mixed lots.
+#[derive(Debug, Clone)]
+pub struct LotDetector {
+    mixed: Regex,
+    quantity: Regex,
+    playset: Regex,
+}
+
+impl Default for LotDetector {
+    fn default() -> Self {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .expect("Lot patterns should be valid")
+        };
+
+        Self {
+            // INFO: "Lot of 10" and "10 card lot" rarely mean ten copies of one card, so lots
+            // are only treated as quantities when written as "x10" or "10x"
+            mixed: build(
+                r"\b(?:bundle|job\s+lot|bulk|collection|mixed|joblot|multi[\s-]?listing|multi[\s-]?buy|(?:choose|pick|select)\s+(?:your|a|any)\s+cards?|lot\s+of\s+\d+|\d+\s+cards?\s+lot|\d+\s+cards)\b",
+            ),
+            quantity: build(r"(?:\bx\s?(\d{1,3})\b|\b(\d{1,3})\s?x\b|\b(\d{1,3})\s+copies\b)"),
+            playset: build(r"\bplay\s?set\b"),
+        }
+    }
+}
+
+impl LotDetector {
+    pub fn detect(&self, title: &str) -> Lot {
+        if self.mixed.is_match(title) {
+            return Lot::Mixed;
+        }
+
+        let mut quantities = self
+            .quantity
+            .captures_iter(title)
+            .filter_map(|x| {
+                x.iter()
+                    .skip(1)
+                    .flatten()
+                    .next()
+                    .and_then(|x| x.as_str().parse::<u32>().ok())
+            })
+            .filter(|x| *x > 0)
+            .collect::<Vec<_>>();
+
+        if self.playset.is_match(title) {
+            quantities.push(4);
+        }
+
+        quantities.sort();
+        quantities.dedup();
+
+        match quantities[..] {
+            [] | [1] => Lot::Single,
+            [x] => Lot::Quantity(x),
+            // INFO: Different quantities in one title are different cards, i.e. "x2 Pikachu x3 Raichu"
+            _ => Lot::Mixed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::currency::GBP;
+
+    fn detect(title: &str) -> Lot {
+        LotDetector::default().detect(title)
+    }
+
+    #[test]
+    fn it_detects_single_cards() {
+        let titles = [
+            "Charizard ex 125/197 Obsidian Flames Double Rare Pokemon Card",
+            "Pikachu ex 238/191 Surging Sparks PSA 10",
+            "1x Iono 185/193 Paldea Evolved Full Art",
+            "Boss's Orders 172/193 x1 Near Mint",
+            "Mew ex 151/165 Special Illustration Rare 151 Set",
+        ];
+
+        for title in titles {
+            assert_eq!(detect(title), Lot::Single, "{title}");
+        }
+    }
+
+    #[test]
+    fn it_detects_quantities_of_one_card() {
+        let cases = [
+            ("Rare Candy x4 Scarlet & Violet 191/198", Lot::Quantity(4)),
+            ("4x Ultra Ball 196/198 Pokemon TCG", Lot::Quantity(4)),
+            ("3 x Nest Ball 181/198 SVI", Lot::Quantity(3)),
+            ("Arven 166/198 Playset Scarlet Violet", Lot::Quantity(4)),
+            ("Iono 185/193 2 copies Paldea Evolved", Lot::Quantity(2)),
+            ("Counter Catcher X 2 Paradox Rift 160/182", Lot::Quantity(2)),
+        ];
+
+        for (title, expected) in cases {
+            assert_eq!(detect(title), expected, "{title}");
+        }
+    }
+
+    #[test]
+    fn it_detects_mixed_lots() {
+        let titles = [
+            "Pokemon Obsidian Flames Bundle Charizard ex 125/197",
+            "Lot of 10 Pokemon Cards Temporal Forces Holo Rares",
+            "Temporal Forces Choose Your Card Reverse Holo Commons",
+            "Pokemon Job Lot 50 Cards inc Pikachu",
+            "x2 Pikachu 027/198 x3 Raichu 028/198",
+            "Scarlet & Violet Reverse Holo Pick A Card",
+            "Obsidian Flames 20 Card Lot Charizard",
+            "Pokemon Bulk Commons Uncommons",
+        ];
+
+        for title in titles {
+            assert_eq!(detect(title), Lot::Mixed, "{title}");
+        }
+    }
+
+    #[test]
+    fn it_apportions_prices() {
+        let gbp = |x| Money::new(x, GBP);
+
+        assert_eq!(Lot::Single.unit_price(gbp(499)), Some(gbp(499)));
+        assert_eq!(Lot::Quantity(4).unit_price(gbp(1000)), Some(gbp(250)));
+        assert_eq!(Lot::Quantity(3).unit_price(gbp(1000)), Some(gbp(333)));
+        assert_eq!(Lot::Quantity(3).unit_price(gbp(1001)), Some(gbp(334)));
+        assert_eq!(Lot::Quantity(2).unit_price(gbp(5)), Some(gbp(3)));
+        assert_eq!(Lot::Quantity(0).unit_price(gbp(1000)), None);
+        assert_eq!(Lot::Mixed.unit_price(gbp(1000)), None);
+    }
+
+    #[test]
+    fn it_shares_postage_between_cards() {
+        let gbp = |x| Money::new(x, GBP);
+
+        assert_eq!(
+            Lot::Single.delivered_unit_price(gbp(99), Some(gbp(400))),
+            Some(gbp(499))
+        );
+        assert_eq!(
+            Lot::Quantity(4).delivered_unit_price(gbp(1000), Some(gbp(155))),
+            Some(gbp(289))
+        );
+        assert_eq!(
+            Lot::Single.delivered_unit_price(gbp(99), Some(Money::zero(GBP))),
+            Some(gbp(99))
+        );
+        assert_eq!(Lot::Single.delivered_unit_price(gbp(99), None), None);
+        assert_eq!(
+            Lot::Mixed.delivered_unit_price(gbp(99), Some(gbp(400))),
+            None
+        );
+    }
+}
@@ -1,11 +1,41 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::sqlite::Sqlite;
-use thirtyfour::{By, Capabilities, WebDriver};
-
-use crate::currency::{Money, GBP};
+use thirtyfour::Capabilities;
+
+use crate::{
+    currency::Money,
+    expansion_registry, monitoring,
+    rejected_listings::{self, Rejection, RejectionReason},
+    scrape_failures::{self, Artifacts, ScrapeFailure},
+};
+
+use browser::Browser;
+use card_details::CardDetails;
+use control::{CurrentCard, ScrapeRequest, ScraperControl};
+use grading::{Grade, GradeParser};
+use lots::LotDetector;
+use retry::{RetryPolicy, ScrapeError};
+use schedule::{DueCard, Schedule};
+use source::{MarketplaceSource, ParseError, ResultsPage};
+use title_rules::TitleRules;
+
+pub mod browser;
+pub mod card_details;
+pub mod control;
+pub mod grading;
+pub mod lots;
+pub mod retry;
+pub mod schedule;
+pub mod scope;
+pub mod source;
+#[cfg(test)]
+mod testing;
+pub mod title_rules;
 
 const PAGINATION_LIMIT: usize = 100;
+/// Due cards fetched at a time, after which the schedule is checked again.
+const DUE_BATCH_SIZE: u32 = 50;
 
 #[derive(Debug, Deserialize, Clone)]
 pub enum Rarity {
@@ -64,7 +94,7 @@ impl std::fmt::Display for Class {
 
 #[derive(Debug, Serialize)]
 #[allow(dead_code)]
-enum BuyingFormat {
+pub enum BuyingFormat {
     // TODO: Look into accepts_offers
     Auction {
         bids: usize,
@@ -105,14 +135,64 @@ impl BuyingFormat {
 
 #[derive(Debug)]
 #[allow(dead_code)]
-struct Listing<'a> {
+pub struct Listing {
     id: usize,
+    source: &'static str,
     title: String,
     date: NaiveDate,
-    price: Money<'a>,
+    price: Money,
+    /// `None` when the listing didn't say, and zero when postage was free.
+    postage: Option<Money>,
+    link: String,
+    buying_format: BuyingFormat,
+    grade: Option<Grade>,
+    /// Names of the title rules that flagged the listing for review.
+    flags: Vec<String>,
+}
+
+/// A listing that was still for sale when it was scraped.
+#[derive(Debug)]
+pub struct ActiveListing {
+    id: usize,
+    source: &'static str,
+    title: String,
+    price: Money,
+    /// `None` when the listing didn't say, and zero when postage was free.
+    postage: Option<Money>,
     link: String,
     buying_format: BuyingFormat,
-    grading_company: Option<u32>,
+    grade: Option<Grade>,
+    /// Names of the title rules that flagged the listing for review.
+    flags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeMode {
+    /// Sold and completed listings, used for pricing.
+    Sold,
+    /// Listings that are still for sale, used to track supply.
+    Active,
+}
+
+impl ScrapeMode {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Sold => "sold",
+            Self::Active => "active",
+        }
+    }
+}
+
+impl std::str::FromStr for ScrapeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sold" => Ok(Self::Sold),
+            "active" => Ok(Self::Active),
+            _ => Err(format!("Unknown scrape mode {s}")),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -123,15 +203,26 @@ pub struct Pokemon {
     pub rarity: Rarity,
     #[serde(rename = "variants")]
     pub class: Vec<Class>,
+    pub image_url: String,
+    pub regulation_mark: Option<String>,
+    pub illustrator: Option<String>,
+    #[serde(rename = "card_varient")]
+    pub details: CardDetails,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct Expansion {
+    /// Database id, set once the expansion has been registered.
+    #[serde(skip)]
+    pub id: u32,
     pub set_name: String,
     pub expansion_name: String,
     pub expansion_number: f32,
     pub expansion_total: usize,
+    #[serde(rename = "expansion_abbreviation")]
+    pub set_code: Option<String>,
+    pub release_date: Option<NaiveDate>,
     pub cards: Vec<Pokemon>,
 }
 
@@ -148,9 +239,186 @@ impl Eq for Expansion {}
 pub struct CardScaper {
     pool: sqlx::Pool<Sqlite>,
     shutdown_rx: std::sync::Arc<tokio::sync::Notify>,
-    sleep_seconds: u64,
+    control: std::sync::Arc<ScraperControl>,
     web_driver_url: String,
     web_driver_capabilities: Capabilities,
+    config: ScraperConfig,
+}
+
+/// What to scrape and how often.
+#[derive(Debug)]
+pub struct ScraperConfig {
+    /// Longest the scraper sleeps before checking the schedule again.
+    pub sleep_seconds: u64,
+    pub sources: Vec<Box<dyn MarketplaceSource>>,
+    pub modes: Vec<ScrapeMode>,
+    pub title_rules: Reloadable<TitleRules>,
+    pub schedule: Reloadable<Schedule>,
+    pub retry: RetryPolicy,
+    /// Where the screenshot and page source of failed cards are saved.
+    pub artifacts_dir: std::path::PathBuf,
+}
+
+/// A file that's reloaded every pass, so that changes are picked up without a restart.
+#[derive(Debug, Clone)]
+pub struct Reloadable<T> {
+    path: std::path::PathBuf,
+    load: fn(&std::path::Path) -> Result<T, String>,
+    /// The last version of the file that loaded.
+    current: T,
+}
+
+impl<T: Clone> Reloadable<T> {
+    /// Loads the file for the first time, which fails rather than falling back so that mistakes
+    /// are caught at startup.
+    pub fn load(
+        path: impl Into<std::path::PathBuf>,
+        load: fn(&std::path::Path) -> Result<T, String>,
+    ) -> Result<Self, String> {
+        let path = path.into();
+        let current = load(&path)?;
+
+        Ok(Self {
+            path,
+            load,
+            current,
+        })
+    }
+
+    /// Reloads the file, keeping the last version that loaded if it can't be read.
+    pub fn reload(&mut self) -> Result<(), String> {
+        self.current = (self.load)(&self.path)?;
+        Ok(())
+    }
+
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+}
+
+/// Everything a listing title is checked against before the listing is stored.
+#[derive(Debug)]
+struct ListingFilters {
+    grades: GradeParser,
+    title_rules: TitleRules,
+    lots: LotDetector,
+}
+
+impl ListingFilters {
+    /// Sets the grade and flags of a listing from its title, returning the rule that rejected it.
+    fn apply(
+        &self,
+        card: &Pokemon,
+        expansion: &Expansion,
+        title: &str,
+        grade: &mut Option<Grade>,
+        flags: &mut Vec<String>,
+    ) -> Result<(), RejectionReason> {
+        *grade = self.grades.parse(title);
+
+        let classification = self.title_rules.classify(card, expansion, title);
+        if let Some(rule) = classification.rejected_by {
+            tracing::debug!(title, rule, "Listing rejected by title rule");
+            return Err(RejectionReason::TitleRule(rule));
+        }
+
+        *flags = classification.flagged_by;
+        Ok(())
+    }
+}
+
+/// Counts the pages and listings seen while scraping one source in one mode.
+#[derive(Debug, Clone, Copy)]
+struct ListingCounters {
+    source: &'static str,
+    mode: ScrapeMode,
+}
+
+impl ListingCounters {
+    fn page<T>(&self, page: &ResultsPage<T>) {
+        let (source, mode) = (self.source, self.mode.id());
+
+        metrics::counter!(monitoring::PAGES_VISITED, "source" => source, "mode" => mode)
+            .increment(1);
+        metrics::counter!(monitoring::LISTINGS_FOUND, "source" => source, "mode" => mode)
+            .increment(page.listings.len() as u64);
+
+        for err in page.listings.iter().filter_map(|x| x.as_ref().err()) {
+            metrics::counter!(monitoring::PARSE_FAILURES, "source" => source, "kind" => err.kind())
+                .increment(1);
+        }
+    }
+
+    fn accepted(&self) {
+        metrics::counter!(
+            monitoring::LISTINGS_ACCEPTED,
+            "source" => self.source,
+            "mode" => self.mode.id(),
+        )
+        .increment(1);
+    }
+
+    fn rejected(&self, reason: &RejectionReason) {
+        metrics::counter!(
+            monitoring::LISTINGS_REJECTED,
+            "source" => self.source,
+            "mode" => self.mode.id(),
+            "reason" => reason.code(),
+            "rule" => reason.rule().unwrap_or_default().to_owned(),
+        )
+        .increment(1);
+    }
+}
+
+/// Rejects a listing that failed to parse, keeping it for review if only its price couldn't be
+/// used. Other failures don't keep enough of the listing to review, so only their reason is
+/// returned.
+fn parse_rejection(source: &'static str, err: ParseError) -> Result<Rejection, RejectionReason> {
+    let (price, title, link, reason) = match err {
+        ParseError::InvalidPrice {
+            price, title, link, ..
+        } => (price, title, link, RejectionReason::InvalidPrice),
+        ParseError::PriceRange { price, title, link } => {
+            (price, title, link, RejectionReason::PriceRange)
+        }
+        err => return Err(RejectionReason::ParseError(err.kind())),
+    };
+
+    Ok(Rejection {
+        source,
+        listing_id: None,
+        title,
+        link,
+        date: None,
+        price: None,
+        postage: None,
+        raw_price: Some(price),
+        bids: None,
+        accepts_offers: None,
+        offer_was_accepted: None,
+        grade: None,
+        reason,
+    })
+}
+
+impl Listing {
+    fn into_rejection(self, reason: RejectionReason) -> Rejection {
+        Rejection {
+            source: self.source,
+            listing_id: Some(self.id as i64),
+            title: self.title,
+            link: self.link,
+            date: Some(self.date),
+            price: Some(self.price),
+            postage: self.postage,
+            raw_price: Some(self.price.to_string()),
+            bids: self.buying_format.get_bids().map(|x| x as u32),
+            accepts_offers: self.buying_format.get_accepts_offers(),
+            offer_was_accepted: Some(self.buying_format.get_offer_was_accepted()),
+            grade: self.grade,
+            reason,
+        }
+    }
 }
 
 impl CardScaper {
@@ -159,190 +427,519 @@ impl CardScaper {
         web_driver_url: impl Into<String>,
         web_driver_capabilities: impl Into<Capabilities>,
         shutdown_rx: std::sync::Arc<tokio::sync::Notify>,
-        sleep_seconds: u64,
+        control: std::sync::Arc<ScraperControl>,
+        config: ScraperConfig,
     ) -> Self {
         Self {
             pool,
             shutdown_rx,
-            sleep_seconds,
+            control,
             web_driver_url: web_driver_url.into(),
             web_driver_capabilities: web_driver_capabilities.into(),
+            config,
         }
     }
 
-    pub async fn start_scraping_expansions(
-        &self,
-        expansions: Vec<Expansion>,
-    ) -> Result<(), String> {
-        let grading_companies = sqlx::query_as::<_, (u32, String)>(
-            "SELECT id, LOWER(initials) AS initials FROM grading_companies;",
-        )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to get grading companies: {e}"))?;
+    #[tracing::instrument(name = "scraper", skip_all)]
+    pub async fn start_scraping_expansions(&self) -> Result<(), String> {
+        // INFO: Selecting once around every pass means that shutdown is noticed wherever the
+        // scraper is, including while it's backing off before a retry
+        tokio::select! {
+            _ = self.shutdown_rx.notified() => {
+                tracing::info!("Killing scraper");
+                Ok(())
+            }
+            x = self.scrape_cycles() => x,
+        }
+    }
 
-        let (ei, ci) = sqlx::query_as::<_, (String, f32, u32)>(
-            "SELECT set_name, CAST(expansion AS REAL) AS expansion, number FROM scraper_progress WHERE id = 1",
-        )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to get scraper progress: {e}"))?
-        .and_then(|(set_name, expansion, number)| {
-            expansions
-                .iter()
-                .position(|e| e.set_name == set_name && e.expansion_number == expansion)
-                .map(|i| {
-                    (i, expansions[i].cards.iter().position(|c| c.number == number as usize).unwrap_or_default())
-                })
-        })
-        .unwrap_or_default();
-        let mut ci = Some(ci);
-
-        for expansion in &expansions {
-            expansion
-                .cards
-                .iter()
-                .fold(
-                    sqlx::query(&format!(
-                        "INSERT INTO cards (set_name, expansion, number, class, name, rarity) VALUES {} ON CONFLICT DO NOTHING",
-                        expansion
-                            .cards
-                            .iter()
-                            .flat_map(|card| card.class.iter().map(|_|  "(?,?,?,?,?,?)"))
-                            .collect::<Vec<_>>()
-                            .join(",")
-                    )),
-                    |acc, x| {
-                        x
-                            .class
-                            .iter()
-                            .fold(acc, |acc, c| {
-                                acc
-                                    .bind(expansion.set_name.clone())
-                                    .bind(expansion.expansion_number)
-                                    .bind(x.number as u32)
-                                    .bind(c.to_string())
-                                    .bind(x.name.clone())
-                                    .bind(x.rarity.to_string())
+    async fn scrape_cycles(&self) -> Result<(), String> {
+        let grade_parser = GradeParser::load(&self.pool).await?;
+        let mut title_rules = self.config.title_rules.clone();
+        let mut schedule = self.config.schedule.clone();
+
+        loop {
+            self.control.wait_while_paused().await;
+
+            // INFO: Title rules and the schedule are reloaded every pass so that changes are
+            // picked up without a restart, and a mistake saved into either keeps the last good
+            // version rather than stopping the scraper
+            for e in [title_rules.reload().err(), schedule.reload().err()]
+                .into_iter()
+                .flatten()
+            {
+                tracing::error!(error = e, "Failed to reload, keeping the last good version");
+                self.control.set_error(e);
+            }
+            let filters = ListingFilters {
+                grades: grade_parser.clone(),
+                title_rules: title_rules.current().clone(),
+                lots: LotDetector::default(),
+            };
+            let now = chrono::Utc::now();
+
+            let mut next_run = now + std::time::Duration::from_secs(self.config.sleep_seconds);
+            match self.due_cards(schedule.current(), now).await {
+                Err(e) => {
+                    self.control.set_error(e.to_string());
+                    e.fatal_or_log()?;
+                }
+                Ok((_, due)) if due.is_empty() => {
+                    monitoring::cycle_completed();
+                    match schedule::summary(&self.pool, now).await {
+                        Ok(summary) => {
+                            if let Some(next_due_at) = summary.next_due_at {
+                                next_run = next_run.min(next_due_at);
                             }
-                        )
-                    },
-                )
-                .execute(&self.pool)
-                .await
-                .map_err(|e| format!("Failed to create expansion entries: {e}"))?;
+                        }
+                        Err(e) => {
+                            ScrapeError::database("Failed to summarise scrape schedule", e)
+                                .fatal_or_log()?;
+                        }
+                    }
+                }
+                Ok((expansions, due)) => {
+                    self.control.start_cycle(due.len());
+
+                    match self.connect().await {
+                        Ok(driver) => {
+                            match self.scrape_due(&due, &expansions, &filters, &driver).await {
+                                Ok(true) => {
+                                    tracing::info!(cards = due.len(), "Scraped due cards");
+                                    // INFO: Straight on to the next batch, which keeps the batches
+                                    // short enough for cards that become due in the meantime to
+                                    // jump the queue
+                                    continue;
+                                }
+                                // INFO: The card is still due, so the next batch would scrape it
+                                // again straight away
+                                Ok(false) => {}
+                                Err(a) => {
+                                    tracing::error!(error = a, "Fatal error scraping");
+                                    self.control.set_error(a.clone());
+                                    return Err(a);
+                                }
+                            }
+                        }
+                        // INFO: The WebDriver may only be restarting, so the cards are retried
+                        // after the usual sleep rather than giving up on the whole scraper
+                        Err(e) => {
+                            tracing::error!(
+                                error = e,
+                                "Failed to connect to the WebDriver, skipping due cards"
+                            );
+                            self.control.set_error(e);
+                        }
+                    }
+                }
+            }
+
+            tracing::info!(%next_run, "Sleeping until the next card is due");
+            self.control.sleep_until(next_run);
+
+            self.idle_until(next_run, &filters).await?;
+            tracing::debug!("Sleep completed");
         }
+    }
 
-        loop {
-            let driver = WebDriver::new(
-                self.web_driver_url.clone(),
-                self.web_driver_capabilities.clone(),
-            )
+    /// Brings the schedule in line with the enabled expansions and gets the cards that are due.
+    async fn due_cards(
+        &self,
+        schedule: &Schedule,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(Vec<Expansion>, Vec<DueCard>), ScrapeError> {
+        let expansions = expansion_registry::enabled_expansions(&self.pool).await?;
+        schedule::sync(&self.pool, &expansions, schedule, now).await?;
+        let due = schedule::due(&self.pool, now, DUE_BATCH_SIZE)
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| ScrapeError::database("Failed to get due cards", e))?;
 
-            for expansion in &expansions[ei..] {
-                tokio::select! {
-                    _ = self.shutdown_rx.notified() => {
-                        println!("Killing scraper");
+        Ok((expansions, due))
+    }
+
+    /// Sleeps until the next card is due, scraping anything that is queued in the meantime.
+    ///
+    /// Returns early if a pass is triggered.
+    async fn idle_until(
+        &self,
+        next_run: chrono::DateTime<chrono::Utc>,
+        filters: &ListingFilters,
+    ) -> Result<(), String> {
+        loop {
+            if self.control.has_queued() {
+                match self.connect().await {
+                    Ok(driver) => self.scrape_queued(filters, &driver).await?,
+                    Err(e) => {
+                        tracing::error!(error = e, "Failed to connect to the WebDriver");
+                        self.control.set_error(e);
+                    }
+                }
+            }
+
+            let remaining = (next_run - chrono::Utc::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(remaining) => return Ok(()),
+                triggered = self.control.woken() => {
+                    if triggered {
+                        tracing::info!("Scrape pass triggered");
                         return Ok(());
                     }
-                    x = self.scrape_expansion(expansion, ci.take().unwrap_or_default(), &grading_companies, &driver) => {
-                        if let Err(a) = x {
-                            println!("Something went wrong scraping: {a:?}");
+                }
+            }
+        }
+    }
 
-                            let timestamp = format!("screenshots/{}.png", chrono::Utc::now().to_rfc3339());
-                            if let Err(e) = driver.screenshot(std::path::Path::new(&timestamp)).await {
-                                println!("Failed to take screenshot {e:?}");
-                            }
+    /// Connects to the WebDriver, retrying with the same backoff as cards.
+    async fn connect(&self) -> Result<Browser, String> {
+        let mut retry = 0;
+        loop {
+            match Browser::connect(&self.web_driver_url, self.web_driver_capabilities.clone()).await
+            {
+                Ok(driver) => return Ok(driver),
+                Err(e) if retry < self.config.retry.retries => {
+                    retry += 1;
+                    tracing::warn!(retry, error = %e, "Retrying WebDriver connection");
+                    tokio::time::sleep(self.config.retry.delay(retry)).await;
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
 
-                            return Err(a);
-                        }
-                        println!("Now sleeping");
-                    }
-                };
+    /// Scrapes every queued request, returning early only on fatal errors.
+    async fn scrape_queued(
+        &self,
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<(), String> {
+        while let Some(request) = self.control.next_queued() {
+            match self.scrape_request(&request, filters, driver).await {
+                Ok(()) => {}
+                Err(ScrapeError::Fatal(e)) => return Err(e),
+                Err(ScrapeError::Recoverable(e)) => {
+                    tracing::error!(?request, error = e, "Failed to scrape queued request");
+                    self.control.set_error(e);
+                }
             }
+        }
 
-            drop(driver);
+        Ok(())
+    }
 
-            sqlx::query("DELETE FROM scraper_progress")
-                .execute(&self.pool)
+    #[tracing::instrument(skip(self, filters, driver))]
+    async fn scrape_request(
+        &self,
+        request: &ScrapeRequest,
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<(), ScrapeError> {
+        let expansion = expansion_registry::get(&self.pool, request.expansion_id)
+            .await
+            .map_err(ScrapeError::Recoverable)?
+            .ok_or(ScrapeError::Recoverable(format!(
+                "Expansion {} isn't registered",
+                request.expansion_id
+            )))?;
+
+        for card in by_class(&expansion.cards)
+            .iter()
+            .filter(|card| request.matches(card))
+        {
+            self.scrape_card_with_retries(card, &expansion, filters, driver)
                 .await
-                .map_err(|e| format!("Failed to delete scraper progress: {e}"))?;
+                .map_err(ScrapeError::Fatal)?;
+        }
 
-            tokio::select! {
-                _ = self.shutdown_rx.notified() => {
-                    println!("Killing scraper");
-                    return Ok(());
+        Ok(())
+    }
+
+    /// Scrapes due cards in order, scheduling each one's next scrape once it's been attempted.
+    ///
+    /// Returns whether every card was scheduled, stopping at the first that couldn't be.
+    async fn scrape_due(
+        &self,
+        due: &[DueCard],
+        expansions: &[Expansion],
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<bool, String> {
+        let mut durations = std::collections::HashMap::<&str, std::time::Duration>::new();
+        let mut completed = true;
+
+        for entry in due {
+            // INFO: Queued scrapes jump ahead of the schedule rather than waiting for it
+            self.scrape_queued(filters, driver).await?;
+
+            // INFO: Only missing if the manifest changed since the schedule was synced, and it's
+            // removed so that it isn't due forever if the card is gone
+            let Some((expansion, card)) = entry.find(expansions) else {
+                if let Err(e) = schedule::remove(&self.pool, entry).await {
+                    ScrapeError::database("Failed to unschedule card", e).fatal_or_log()?;
                 }
-                _ = tokio::time::sleep(std::time::Duration::from_secs(self.sleep_seconds)) => {
-                    println!("Sleep completed");
+                continue;
+            };
+
+            let start = std::time::Instant::now();
+            self.scrape_card_with_retries(&card, expansion, filters, driver)
+                .await?;
+            let scheduled = schedule::mark_attempted(&self.pool, entry, chrono::Utc::now()).await;
+            self.control.card_scraped();
+
+            *durations
+                .entry(expansion.expansion_name.as_str())
+                .or_default() += start.elapsed();
+
+            if let Err(e) = scheduled {
+                match ScrapeError::database("Failed to schedule card", e) {
+                    ScrapeError::Fatal(e) => return Err(e),
+                    ScrapeError::Recoverable(e) => {
+                        tracing::error!(error = e, "Ending the pass early");
+                        self.control.set_error(e);
+                        completed = false;
+                        break;
+                    }
                 }
             }
         }
+
+        for (expansion, duration) in durations {
+            metrics::histogram!(monitoring::EXPANSION_DURATION, "expansion" => expansion.to_owned())
+                .record(duration);
+        }
+
+        Ok(completed)
     }
 
-    async fn scrape_expansion(
+    /// Scrapes a card, retrying with backoff and then skipping it if it keeps failing.
+    ///
+    /// Only returns an error if the failure is fatal.
+    #[tracing::instrument(
+        name = "card",
+        skip_all,
+        fields(
+            set = expansion.set_name,
+            expansion = expansion.expansion_name,
+            number = card.number,
+            class = %card.class.first().unwrap(),
+            name = card.name,
+        )
+    )]
+    async fn scrape_card_with_retries(
         &self,
+        card: &Pokemon,
         expansion: &Expansion,
-        card_start: usize,
-        grading_companies: &Vec<(u32, String)>,
-        driver: &WebDriver,
+        filters: &ListingFilters,
+        driver: &Browser,
     ) -> Result<(), String> {
-        let cards = &expansion.cards[card_start..]
-            .iter()
-            .flat_map(|card| {
-                card.class.iter().map(|class| Pokemon {
-                    name: card.name.clone(),
-                    number: card.number,
-                    rarity: card.rarity.clone(),
-                    class: vec![class.clone()],
-                })
-            })
-            .collect::<Vec<_>>();
+        let class = card.class.first().unwrap().to_string();
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let error = match self.scrape_card(card, expansion, filters, driver).await {
+                Ok(()) => return Ok(()),
+                Err(ScrapeError::Fatal(e)) => return Err(e),
+                Err(ScrapeError::Recoverable(e)) => e,
+            };
+
+            let gave_up = attempt > self.config.retry.retries;
+            tracing::warn!(attempt, gave_up, error, "Failed to scrape card");
+            self.control.set_error(error.clone());
+            metrics::counter!(
+                monitoring::CARD_FAILURES,
+                "expansion" => expansion.expansion_name.clone(),
+                "gave_up" => gave_up.to_string(),
+            )
+            .increment(1);
+
+            let failure = ScrapeFailure {
+                expansion_id: expansion.id,
+                number: card.number as u32,
+                class: &class,
+                attempt,
+                error: &error,
+                gave_up,
+            };
+            match scrape_failures::record(&self.pool, &failure).await {
+                Ok(failure_id) => {
+                    match self
+                        .capture_artifacts(failure_id, card, expansion, &error, driver)
+                        .await
+                    {
+                        Ok(artifacts) => {
+                            if let Err(e) =
+                                scrape_failures::record_artifacts(&self.pool, &artifacts).await
+                            {
+                                ScrapeError::database("Failed to record failure artifacts", e)
+                                    .fatal_or_log()?;
+                            }
+                        }
+                        Err(e) => tracing::error!(error = e, "Failed to capture failure artifacts"),
+                    }
+                }
+                Err(e) => {
+                    ScrapeError::database("Failed to record scrape failure", e).fatal_or_log()?
+                }
+            }
+
+            if gave_up {
+                return Ok(());
+            }
+
+            tokio::time::sleep(self.config.retry.delay(attempt)).await;
+        }
+    }
+
+    /// Saves what the browser was showing when a card failed, so that the page can be replayed
+    /// against the parser later.
+    async fn capture_artifacts(
+        &self,
+        failure_id: u32,
+        card: &Pokemon,
+        expansion: &Expansion,
+        error: &str,
+        driver: &Browser,
+    ) -> Result<Artifacts, String> {
+        let captured_at = chrono::Utc::now();
+        let directory = self.config.artifacts_dir.join(format!(
+            "{}-{failure_id}",
+            captured_at.format("%Y%m%dT%H%M%SZ")
+        ));
+        tokio::fs::create_dir_all(&directory)
+            .await
+            .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+
+        // INFO: Each part is captured on its own as the browser may be in no state to answer
+        let has_screenshot = driver
+            .screenshot(&directory.join(scrape_failures::SCREENSHOT_FILE))
+            .await
+            .is_ok();
+        let has_page_source = match driver.source().await {
+            Ok(html) => tokio::fs::write(directory.join(scrape_failures::PAGE_SOURCE_FILE), html)
+                .await
+                .is_ok(),
+            Err(_) => false,
+        };
+        let url = driver.current_url().await.ok();
+
+        let summary = serde_json::json!({
+            "failure_id": failure_id,
+            "expansion_id": expansion.id,
+            "set_name": expansion.set_name,
+            "expansion_name": expansion.expansion_name,
+            "set_code": expansion.set_code,
+            "number": card.number,
+            "class": card.class.first().unwrap().to_string(),
+            "name": card.name,
+            "url": url,
+            "error": error,
+            "captured_at": captured_at,
+        });
+        tokio::fs::write(
+            directory.join(scrape_failures::SUMMARY_FILE),
+            serde_json::to_vec_pretty(&summary).map_err(|e| e.to_string())?,
+        )
+        .await
+        .map_err(|e| format!("Failed to write failure summary: {e}"))?;
+
+        Ok(Artifacts {
+            scrape_failure_id: failure_id,
+            directory: directory.to_string_lossy().into_owned(),
+            url,
+            has_screenshot,
+            has_page_source,
+        })
+    }
+
+    async fn scrape_card(
+        &self,
+        card: &Pokemon,
+        expansion: &Expansion,
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<(), ScrapeError> {
+        self.control.wait_while_paused().await;
+        self.control.set_current(Some(CurrentCard {
+            expansion_id: expansion.id,
+            set_name: expansion.set_name.clone(),
+            expansion_name: expansion.expansion_name.clone(),
+            number: card.number as u32,
+            class: card.class.first().unwrap().to_string(),
+        }));
+
+        let start = std::time::Instant::now();
+        let mut final_listings = Vec::new();
+        let mut rejections = Vec::new();
+
+        for source in &self.config.sources {
+            if self.config.modes.contains(&ScrapeMode::Active) {
+                self.scrape_active_listings_for_card(
+                    source.as_ref(),
+                    card,
+                    expansion,
+                    filters,
+                    driver,
+                )
+                .await
+                .map_err(|e| {
+                    ScrapeError::scraping(
+                        &format!("Failed to scrape active listings from {}", source.id()),
+                        e,
+                    )
+                })?;
+            }
+
+            if !self.config.modes.contains(&ScrapeMode::Sold) {
+                continue;
+            }
 
-        for card in cards {
             let last_listing_date = sqlx::query_as::<_, (chrono::NaiveDate,)>(
                 "
                     SELECT date
                     FROM listings
                     JOIN listings_cards
                       ON listings_cards.listing_id = listings.id
-                    WHERE listings_cards.card_set_name = ?
-                      AND listings_cards.card_expansion = ?
+                    WHERE listings_cards.card_expansion_id = ?
                       AND listings_cards.card_number = ?
                       AND listings_cards.card_class = ?
+                      AND listings.source = ?
                     ORDER BY date DESC
                     LIMIT 1
                     ",
             )
-            .bind(expansion.set_name.clone())
-            .bind(expansion.expansion_number)
+            .bind(expansion.id)
             .bind(card.number as u32)
             .bind(card.class.first().unwrap().to_string())
+            .bind(source.id())
             .fetch_optional(&self.pool)
             .await
-            .map_err(|e| format!("Failed to last listing date: {e}"))?
+            .map_err(|e| ScrapeError::database("Failed to last listing date", e))?
             .map(|x| x.0);
 
-            let final_listings = self
+            let (listings, rejected) = self
                 .scrape_listings_for_card(
+                    source.as_ref(),
                     card,
                     expansion,
                     last_listing_date,
-                    grading_companies,
+                    filters,
                     driver,
                 )
                 .await
-                .map_err(|e| format!("Failed to scrape card: {e:?}"))?;
+                .map_err(|e| {
+                    ScrapeError::scraping(&format!("Failed to scrape card from {}", source.id()), e)
+                })?;
+            final_listings.extend(listings);
+            rejections.extend(rejected);
+        }
 
-            let mut txn = self
-                .pool
-                .begin()
-                .await
-                .map_err(|e| format!("Error creating transaction: {e}"))?;
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ScrapeError::database("Error creating transaction", e))?;
 
-            let r: Result<(), sqlx::error::Error> = async {
+        let r: Result<(), sqlx::error::Error> = async {
                 if !final_listings.is_empty() {
                     final_listings
                         .iter()
@@ -350,26 +947,43 @@ impl CardScaper {
                             sqlx::query(&format!(
                                 "
                                 INSERT INTO listings
-                                    (id, title, date, price, link, bids, accepts_offers, offer_was_accepted, graded_by) 
+                                    (id, title, date, price, currency, postage, link, bids, accepts_offers, offer_was_accepted, graded_by, grade, grade_qualifier, flagged_by, lot_kind, quantity, unit_price, delivered_unit_price, source) 
                                 VALUES {} 
                                 ON CONFLICT DO NOTHING
                                 ",
                                 final_listings
                                     .iter()
-                                    .map(|_| "(?,?,?,?,?,?,?,?,?)")
+                                    .map(|_| "(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)")
                                     .collect::<Vec<_>>()
                                     .join(",")
                             )),
                             |acc, x| {
-                                acc.bind(x.id as u32)
+                                let lot = filters.lots.detect(&x.title);
+
+                                acc.bind(x.id as i64)
                                     .bind(x.title.clone())
                                     .bind(x.date)
-                                    .bind(std::convert::Into::<u64>::into(&x.price) as u32)
+                                    .bind(x.price)
+                                    .bind(x.price.currency())
+                                    .bind(x.postage)
                                     .bind(x.link.clone())
                                     .bind(x.buying_format.get_bids().map(|x| x as u32))
                                     .bind(x.buying_format.get_accepts_offers())
                                     .bind(x.buying_format.get_offer_was_accepted())
-                                    .bind(x.grading_company)
+                                    .bind(x.grade.as_ref().map(|x| x.company_id))
+                                    .bind(x.grade.as_ref().and_then(|x| x.grade))
+                                    .bind(
+                                        x.grade
+                                            .as_ref()
+                                            .and_then(|x| x.qualifier)
+                                            .map(|x| x.to_string()),
+                                    )
+                                    .bind((!x.flags.is_empty()).then(|| x.flags.join(",")))
+                                    .bind(lot.kind())
+                                    .bind(lot.quantity())
+                                    .bind(lot.unit_price(x.price))
+                                    .bind(lot.delivered_unit_price(x.price, x.postage))
+                                    .bind(x.source)
                             },
                         )
                         .execute(&mut *txn)
@@ -381,20 +995,19 @@ impl CardScaper {
                             sqlx::query(&format!(
                                 "
                                 INSERT INTO listings_cards
-                                    (listing_id, card_set_name, card_expansion, card_number, card_class) 
+                                    (listing_id, card_expansion_id, card_number, card_class) 
                                 VALUES {} 
                                 ON CONFLICT DO NOTHING
                                 ",
                                 final_listings
                                     .iter()
-                                    .map(|_| "(?,?,?,?,?)")
+                                    .map(|_| "(?,?,?,?)")
                                     .collect::<Vec<_>>()
                                     .join(",")
                             )),
                             |acc, x| acc
-                                .bind(x.id as u32)
-                                .bind(expansion.set_name.clone())
-                                .bind(expansion.expansion_number )
+                                .bind(x.id as i64)
+                                .bind(expansion.id)
                                 .bind(card.number as u32)
                                 .bind(card.class.first().unwrap().to_string()),
                         )
@@ -403,364 +1016,354 @@ impl CardScaper {
 
                 }
 
-                sqlx::query(
-                    "
-                    INSERT OR REPLACE INTO scraper_progress 
-                        (id, set_name, expansion, number, class)
-                    VALUES
-                        (1, ?, ?, ?, ?)"
-                )
-                    .bind(expansion.set_name.clone())
-                    .bind(expansion.expansion_number )
-                    .bind(card.number as u32)
-                    .bind(card.class.first().unwrap().to_string())
-                    .execute(&mut *txn)
+                let class = card.class.first().unwrap().to_string();
+                for rejection in &rejections {
+                    rejected_listings::record(
+                        &mut txn,
+                        rejection,
+                        (expansion.id, card.number as u32, &class),
+                    )
                     .await?;
+                }
 
                 Ok(())
             }.await;
 
-            match r {
-                err @ Err(_) => {
-                    if let Err(e2) = txn.rollback().await {
-                        eprintln!("Failed to rollback transaction with Error: {e2}");
-                    }
-                    err
+        match r {
+            err @ Err(_) => {
+                if let Err(e2) = txn.rollback().await {
+                    tracing::error!("Failed to rollback transaction with Error: {e2}");
                 }
-                Ok(_) => txn.commit().await,
+                err
             }
-            .map_err(|e| format!("Failed to create listing: {e}"))?;
+            Ok(_) => txn.commit().await,
         }
+        .map_err(|e| ScrapeError::database("Failed to create listing", e))?;
+
+        tracing::info!(
+            listings = final_listings.len(),
+            rejected = rejections.len(),
+            "Scraped card"
+        );
+
+        let expansion_name = expansion.expansion_name.clone();
+        metrics::counter!(monitoring::CARDS_SCRAPED, "expansion" => expansion_name.clone())
+            .increment(1);
+        metrics::histogram!(monitoring::CARD_DURATION, "expansion" => expansion_name)
+            .record(start.elapsed());
 
         Ok(())
     }
 
+    #[tracing::instrument(name = "sold", skip_all, fields(source = source.id()))]
     async fn scrape_listings_for_card<'a>(
         &self,
+        source: &dyn MarketplaceSource,
         card: &Pokemon,
         expansion: &Expansion,
         last_listing_date: Option<chrono::NaiveDate>,
-        grading_companies: &Vec<(u32, String)>,
-        driver: &WebDriver,
-    ) -> Result<Vec<Listing<'a>>, Box<dyn std::error::Error>> {
-        // TODO: Consider clearing the text box
-        driver.goto("https://ebay.co.uk").await?;
-
-        println!("{card:#?}");
-
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<(Vec<Listing>, Vec<Rejection>), Box<dyn std::error::Error>> {
         driver
-            .find(By::Id("gh-ac"))
-            .await?
-            .send_keys(format!(
-                "{} {:0>3}/{}",
-                card.name, card.number, expansion.expansion_total
-            ))
+            .goto(&source.search_url(card, expansion, ScrapeMode::Sold)?)
             .await?;
 
-        match driver.find(By::Id("gh-btn")).await {
-            btn @ Ok(_) => btn,
-            Err(_) => match driver.find(By::Id("gh-search-btn")).await {
-                btn @ Ok(_) => btn,
-                err => err,
-            },
-        }?
-        .click()
-        .await?;
-
-        // Change page count to 240
-        if let Some(url) = match driver
-            .find(By::Css("#srp-ipp-menu-content li:last-child a"))
-            .await
-        {
-            Ok(x) => x.prop("href").await,
-            Err(err) => match err.as_inner() {
-                thirtyfour::error::WebDriverErrorInner::NoSuchElement(_) => Ok(None),
-                _ => Err(err),
-            },
-        }? {
-            driver.goto(url).await?;
-        }
-
-        // INFO: The page takes a while to load so we add retry logic to the first find
-        for i in 0..5 {
-            match driver
-                .find(By::Css("input[type=checkbox][aria-label='Sold items']"))
-                .await
-            {
-                Ok(checkbox) => {
-                    checkbox.click().await?;
-                    break;
-                }
-                e @ Err(_) => {
-                    if i >= 4 {
-                        e?;
-                    }
-                    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-                }
-            }
-        }
-
+        let counters = ListingCounters {
+            source: source.id(),
+            mode: ScrapeMode::Sold,
+        };
         let mut final_listings = Vec::new();
+        let mut rejections = Vec::new();
 
         let mut page_count = 0;
         loop {
-            let listings = driver.find_all(By::Css("ul.srp-results > li")).await?;
-
-            for listing in listings {
-                let Some(class_names) = listing.class_name().await? else {
-                    println!("Couldn't find class_names for listing");
-                    continue;
+            let page = source.parse_sold_page(&driver.source().await?);
+            counters.page(&page);
+
+            for listing in page.listings {
+                let mut listing = match listing {
+                    Ok(listing) => listing,
+                    // INFO: One listing that can't be parsed shouldn't stop the rest of the card
+                    // being stored, and retrying wouldn't parse it any differently
+                    Err(err) => {
+                        let error = err.to_string();
+                        let reason = match parse_rejection(source.id(), err) {
+                            Ok(rejection) => {
+                                let reason = rejection.reason.clone();
+                                rejections.push(rejection);
+                                reason
+                            }
+                            Err(reason) => reason,
+                        };
+                        tracing::warn!(error, %reason, "Failed to parse listing. Skipping.");
+                        counters.rejected(&reason);
+                        continue;
+                    }
                 };
 
-                if !class_names.split_whitespace().any(|x| x == "s-card") {
-                    if listing.text().await? == "Results matching fewer words" {
-                        println!("Reached end of good results");
-                        break;
-                    };
-
-                    continue;
-                }
-
-                let date = NaiveDate::parse_from_str(
-                    listing
-                        .find(By::Css(".s-card__caption"))
-                        .await?
-                        .text()
-                        .await?
-                        .trim_start_matches("Sold "),
-                    "%-d %b %Y",
-                )?;
-
-                if last_listing_date.map(|d| date < d).unwrap_or(false) {
-                    println!(
-                        "Listing date {date} is less than last recorded date {}. Ending.",
+                if last_listing_date.is_some_and(|d| listing.date < d) {
+                    tracing::debug!(
+                        "Listing date {} is less than last recorded date {}. Ending.",
+                        listing.date,
                         last_listing_date.unwrap()
                     );
-                    return Ok(final_listings);
+                    return Ok((final_listings, rejections));
                 }
 
-                let title = listing
-                    .find(By::Css("a > div.s-card__title span"))
-                    .await?
-                    .text()
-                    .await?;
-
-                let lower_case_title = title.to_lowercase();
-
-                let grading_company = grading_companies
-                    .iter()
-                    .find(|(_, initials)| lower_case_title.contains(initials))
-                    .map(|(id, _)| *id);
-
-                if !lower_case_title.contains(&card.name.to_lowercase())
-                    && !card
-                        .name
-                        .to_lowercase()
-                        .split_whitespace()
-                        .all(|x| lower_case_title.contains(x))
-                {
-                    println!("Title \"{}\" doesn't contain card name. Skipping.", title);
+                if let Err(reason) = filters.apply(
+                    card,
+                    expansion,
+                    &listing.title,
+                    &mut listing.grade,
+                    &mut listing.flags,
+                ) {
+                    counters.rejected(&reason);
+                    rejections.push(listing.into_rejection(reason));
                     continue;
                 }
 
-                if match card.class.first().unwrap() {
-                    Class::Regular => ["reverse holo", "reverse"]
-                        .into_iter()
-                        .any(|x| lower_case_title.contains(x)),
-                    Class::ReverseHolo => lower_case_title.contains("regular"),
-                    Class::Foil => false,
-                } {
-                    println!("Title \"{}\" contains blacklisted words. Skipping.", title);
-                    continue;
-                }
+                counters.accepted();
+                final_listings.push(listing);
+            }
 
-                if match card.class.first().unwrap() {
-                    Class::Regular => false,
-                    Class::ReverseHolo => !["reverse holo", "holo", "reverse"]
-                        .into_iter()
-                        .any(|x| lower_case_title.contains(x)),
-                    Class::Foil => false,
-                } {
-                    println!(
-                        "Title \"{}\" doesn't contain whitelisted words. Skipping",
-                        title
-                    );
-                    continue;
-                }
+            let Some(next_page) = page.next_page else {
+                break;
+            };
 
-                let price = listing
-                    .find(By::Css(".s-card__price"))
-                    .await?
-                    .text()
-                    .await?;
+            driver.goto(&next_page).await?;
 
-                let Ok(price) = Money::from_str(price.as_str(), GBP) else {
-                    println!("Failed to parse price {price}. Skipping.");
-                    continue;
-                };
+            page_count += 1;
+            if page_count > PAGINATION_LIMIT {
+                tracing::warn!(limit = PAGINATION_LIMIT, "Reached pagination limit");
+                break;
+            }
+        }
 
-                let link = listing
-                    .find(By::Css(".su-card-container__header a"))
-                    .await?
-                    .prop("href")
-                    .await?
-                    .unwrap_or("".into());
-
-                let link = link
-                    .split("?")
-                    .next()
-                    .expect("One result should always be returned")
-                    .to_string();
-
-                let id = link
-                    .split("/")
-                    .last()
-                    .expect("Split always returns one value")
-                    .parse()?;
-
-                let buying_format = match listing
-                    .find(By::Css(".su-card-container__attributes__primary .s-card__attribute-row:nth-child(2)"))
-                    .await?
-                    .text()
-                    .await?
-                    .as_str()
-                {
-                    "Buy It Now" => BuyingFormat::BuyItNow {
-                        accepts_offers: false,
-                        offer_was_accepted: false,
-                    },
-                    "or Best Offer" => BuyingFormat::BuyItNow {
-                        accepts_offers: true,
-                        offer_was_accepted: false,
-                    },
-                    "Best Offer accepted" => BuyingFormat::BuyItNow {
-                        accepts_offers: true,
-                        offer_was_accepted: true,
-                    },
-                    bids => {
-                        BuyingFormat::Auction {
-                            bids: bids
-                                .split_whitespace()
-                                .next()
-                                .expect("should always have a value")
-                                .parse()
-                                .expect("should always be a number"),
-                            offer_was_accepted: listing
-                                .find(By::Css(".su-card-container__attributes__primary .s-card__attribute-row:nth-child(3)"))
-                                .await?.text().await? == "Best Offer accepted"
-                        }
+        Ok((final_listings, rejections))
+    }
+
+    #[tracing::instrument(name = "active", skip_all, fields(source = source.id()))]
+    async fn scrape_active_listings_for_card(
+        &self,
+        source: &dyn MarketplaceSource,
+        card: &Pokemon,
+        expansion: &Expansion,
+        filters: &ListingFilters,
+        driver: &Browser,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let scraped_at = chrono::Utc::now();
+
+        driver
+            .goto(&source.search_url(card, expansion, ScrapeMode::Active)?)
+            .await?;
+
+        let counters = ListingCounters {
+            source: source.id(),
+            mode: ScrapeMode::Active,
+        };
+        let mut active_listings = Vec::new();
+        // INFO: Listings can only be marked as ended if every page of results was seen
+        let mut complete = false;
+
+        let mut page_count = 0;
+        loop {
+            let page = source.parse_active_page(&driver.source().await?);
+            counters.page(&page);
+
+            for listing in page.listings {
+                let mut listing = match listing {
+                    Ok(listing) => listing,
+                    Err(err) => {
+                        let error = err.to_string();
+                        let reason = match parse_rejection(source.id(), err) {
+                            Ok(rejection) => rejection.reason,
+                            Err(reason) => reason,
+                        };
+                        tracing::warn!(error, %reason, "Failed to parse listing. Skipping.");
+                        counters.rejected(&reason);
+                        continue;
                     }
                 };
 
-                let listing = Listing {
-                    id,
-                    title,
-                    date,
-                    price,
-                    link,
-                    buying_format,
-                    grading_company,
-                };
+                if let Err(reason) = filters.apply(
+                    card,
+                    expansion,
+                    &listing.title,
+                    &mut listing.grade,
+                    &mut listing.flags,
+                ) {
+                    counters.rejected(&reason);
+                    continue;
+                }
 
-                final_listings.push(listing);
+                counters.accepted();
+                active_listings.push(listing);
             }
 
-            match driver.find(By::Css("a.pagination__next")).await {
-                btn @ Ok(_) => btn,
-                Err(err) => match err.as_inner() {
-                    thirtyfour::error::WebDriverErrorInner::NoSuchElement(_) => break,
-                    _ => Err(err),
-                },
-            }?
-            .click()
-            .await?;
+            let Some(next_page) = page.next_page else {
+                complete = true;
+                break;
+            };
+
+            driver.goto(&next_page).await?;
 
             page_count += 1;
             if page_count > PAGINATION_LIMIT {
-                println!("PAGINATION LIMIT");
+                tracing::warn!(limit = PAGINATION_LIMIT, "Reached pagination limit");
                 break;
             }
         }
 
-        Ok(final_listings)
-    }
-}
-
-trait Finder {
-    async fn find(&self, by: By) -> thirtyfour::error::WebDriverResult<thirtyfour::WebElement>;
-    async fn find_all(
-        &self,
-        by: By,
-    ) -> thirtyfour::error::WebDriverResult<Vec<thirtyfour::WebElement>>;
-}
+        let class = card.class.first().unwrap().to_string();
+        let mut txn = self.pool.begin().await?;
 
-impl Finder for std::sync::Arc<thirtyfour::session::handle::SessionHandle> {
-    async fn find(&self, by: By) -> thirtyfour::error::WebDriverResult<thirtyfour::WebElement> {
-        self.find(by).await
-    }
+        for listing in &active_listings {
+            sqlx::query(
+                "
+                INSERT INTO active_listings
+                    (id, source, title, price, currency, postage, link, bids, accepts_offers, graded_by, grade, grade_qualifier, flagged_by, card_expansion_id, card_number, card_class, first_seen, last_seen)
+                VALUES
+                    (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)
+                ON CONFLICT (id, card_expansion_id, card_number, card_class) DO UPDATE SET
+                    title = excluded.title,
+                    price = excluded.price,
+                    currency = excluded.currency,
+                    postage = excluded.postage,
+                    bids = excluded.bids,
+                    accepts_offers = excluded.accepts_offers,
+                    graded_by = excluded.graded_by,
+                    grade = excluded.grade,
+                    grade_qualifier = excluded.grade_qualifier,
+                    flagged_by = excluded.flagged_by,
+                    last_seen = excluded.last_seen,
+                    ended_at = NULL
+                ",
+            )
+            .bind(listing.id as i64)
+            .bind(listing.source)
+            .bind(listing.title.clone())
+            .bind(listing.price)
+            .bind(listing.price.currency())
+            .bind(listing.postage)
+            .bind(listing.link.clone())
+            .bind(listing.buying_format.get_bids().map(|x| x as u32))
+            .bind(listing.buying_format.get_accepts_offers())
+            .bind(listing.grade.as_ref().map(|x| x.company_id))
+            .bind(listing.grade.as_ref().and_then(|x| x.grade))
+            .bind(
+                listing
+                    .grade
+                    .as_ref()
+                    .and_then(|x| x.qualifier)
+                    .map(|x| x.to_string()),
+            )
+            .bind((!listing.flags.is_empty()).then(|| listing.flags.join(",")))
+            .bind(expansion.id)
+            .bind(card.number as u32)
+            .bind(class.clone())
+            .bind(scraped_at)
+            .bind(scraped_at)
+            .execute(&mut *txn)
+            .await?;
+        }
 
-    async fn find_all(
-        &self,
-        by: By,
-    ) -> thirtyfour::error::WebDriverResult<Vec<thirtyfour::WebElement>> {
-        self.find_all(by).await
-    }
-}
+        if complete {
+            sqlx::query(
+                "
+                UPDATE active_listings
+                SET ended_at = ?
+                WHERE source = ?
+                  AND card_expansion_id = ?
+                  AND card_number = ?
+                  AND card_class = ?
+                  AND ended_at IS NULL
+                  AND last_seen < ?
+                ",
+            )
+            .bind(scraped_at)
+            .bind(source.id())
+            .bind(expansion.id)
+            .bind(card.number as u32)
+            .bind(class)
+            .bind(scraped_at)
+            .execute(&mut *txn)
+            .await?;
+        }
 
-impl Finder for thirtyfour::WebElement {
-    async fn find(&self, by: By) -> thirtyfour::error::WebDriverResult<thirtyfour::WebElement> {
-        self.find(by).await
-    }
+        txn.commit().await?;
 
-    async fn find_all(
-        &self,
-        by: By,
-    ) -> thirtyfour::error::WebDriverResult<Vec<thirtyfour::WebElement>> {
-        self.find_all(by).await
+        Ok(())
     }
 }
 
-trait TryFind {
-    async fn try_find(&self, by: By) -> thirtyfour::error::WebDriverResult<thirtyfour::WebElement>;
-    async fn try_find_all(
-        &self,
-        by: By,
-    ) -> thirtyfour::error::WebDriverResult<Vec<thirtyfour::WebElement>>;
+/// Splits cards into one card per class, which is how they are scraped and stored.
+fn by_class(cards: &[Pokemon]) -> Vec<Pokemon> {
+    cards
+        .iter()
+        .flat_map(|card| {
+            card.class.iter().map(|class| Pokemon {
+                class: vec![class.clone()],
+                ..card.clone()
+            })
+        })
+        .collect()
 }
 
-impl<T> TryFind for T
-where
-    T: Finder,
-{
-    async fn try_find(&self, by: By) -> thirtyfour::error::WebDriverResult<thirtyfour::WebElement> {
-        const MAX_RETRIES: usize = 4;
-        const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
-
-        for i in 0..MAX_RETRIES {
-            dbg!(i, &by);
-            if let Ok(element) = self.find(by.clone()).await {
-                return Ok(element);
-            }
-            tokio::time::sleep(RETRY_DELAY).await;
-        }
+#[cfg(test)]
+mod tests {
+
+    use super::*;
 
-        self.find(by).await
+    #[test]
+    fn it_keeps_listings_with_unusable_prices_for_review() {
+        let err = ParseError::PriceRange {
+            price: "£5.00 to £12.00".into(),
+            title: "Charizard ex".into(),
+            link: "https://www.ebay.co.uk/itm/226512345678".into(),
+        };
+
+        let Ok(rejection) = parse_rejection("ebay_uk", err) else {
+            panic!("Listing should be kept for review");
+        };
+        assert_eq!(rejection.reason, RejectionReason::PriceRange);
+        assert_eq!(rejection.raw_price.as_deref(), Some("£5.00 to £12.00"));
     }
 
-    async fn try_find_all(
-        &self,
-        by: By,
-    ) -> thirtyfour::error::WebDriverResult<Vec<thirtyfour::WebElement>> {
-        const MAX_RETRIES: usize = 4;
-        const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
-
-        for _ in 0..MAX_RETRIES {
-            if let Ok(element) = self.find_all(by.clone()).await {
-                return Ok(element);
-            }
-            tokio::time::sleep(RETRY_DELAY).await;
-        }
+    #[test]
+    fn it_skips_listings_that_fail_to_parse() {
+        let err = ParseError::InvalidBuyingFormat("Classified Ad".into());
+
+        assert_eq!(
+            parse_rejection("ebay_uk", err).map(|x| x.reason),
+            Err(RejectionReason::ParseError("invalid_buying_format"))
+        );
+    }
 
-        self.find_all(by).await
+    #[test]
+    fn it_keeps_the_last_good_version_when_a_reload_fails() {
+        let path = std::env::temp_dir().join(format!("reloadable-{}.txt", std::process::id()));
+        let load = |x: &std::path::Path| {
+            std::fs::read_to_string(x)
+                .map_err(|e| e.to_string())?
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| e.to_string())
+        };
+
+        let Ok(()) = std::fs::write(&path, "1") else {
+            panic!("Failed to write file");
+        };
+        let Ok(mut file) = Reloadable::load(&path, load) else {
+            panic!("Failed to load file");
+        };
+        let Ok(()) = std::fs::write(&path, "one") else {
+            panic!("Failed to write file");
+        };
+        let reloaded = file.reload();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(reloaded.is_err());
+        assert_eq!(*file.current(), 1);
     }
 }
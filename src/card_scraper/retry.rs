@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+/// Longest that a card waits between attempts, however many times it has failed.
+const MAX_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// How many times a card is retried before it's skipped for the rest of the cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every retry after it.
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, counting from one.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(MAX_DELAY)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScrapeError {
+    /// Only affects the card being scraped, which can be retried or skipped.
+    Recoverable(String),
+    /// Nothing else can be scraped either, so the scraper has to stop.
+    Fatal(String),
+}
+
+impl ScrapeError {
+    pub fn database(context: &str, error: sqlx::Error) -> Self {
+        let message = chain(context, &error);
+        if is_fatal(&error) {
+            Self::Fatal(message)
+        } else {
+            Self::Recoverable(message)
+        }
+    }
+
+    /// Wraps a scraping error, which is only fatal if it came from the database.
+    pub fn scraping(context: &str, error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast::<sqlx::Error>() {
+            Ok(error) => Self::database(context, *error),
+            Err(error) => Self::Recoverable(chain(context, error.as_ref())),
+        }
+    }
+
+    /// Logs a recoverable error, only returning it if it's fatal.
+    pub fn fatal_or_log(self) -> Result<(), String> {
+        match self {
+            Self::Fatal(message) => Err(message),
+            Self::Recoverable(message) => {
+                tracing::error!("{message}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Formats an error with every error that caused it, i.e. the HTTP error behind a WebDriver
+/// error.
+fn chain(context: &str, error: &dyn std::error::Error) -> String {
+    std::iter::successors(Some(error), |x| x.source())
+        .fold(context.to_string(), |acc, x| format!("{acc}: {x}"))
+}
+
+impl std::fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Recoverable(message) | Self::Fatal(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Whether the database can no longer be reached, as opposed to a single query failing.
+fn is_fatal(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::Io(_)
+            | sqlx::Error::Configuration(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_doubles_the_delay_up_to_a_limit() {
+        let policy = RetryPolicy {
+            retries: 20,
+            base_delay: Duration::from_secs(5),
+        };
+
+        assert_eq!(policy.delay(1), Duration::from_secs(5));
+        assert_eq!(policy.delay(2), Duration::from_secs(10));
+        assert_eq!(policy.delay(4), Duration::from_secs(40));
+        assert_eq!(policy.delay(20), MAX_DELAY);
+    }
+
+    #[test]
+    fn it_only_treats_a_lost_database_as_fatal() {
+        let ScrapeError::Fatal(_) = ScrapeError::database("Query", sqlx::Error::PoolClosed) else {
+            panic!("A closed pool should be fatal");
+        };
+        let ScrapeError::Recoverable(_) = ScrapeError::database("Query", sqlx::Error::RowNotFound)
+        else {
+            panic!("A missing row should be recoverable");
+        };
+        let ScrapeError::Recoverable(_) = ScrapeError::scraping(
+            "Scrape",
+            Box::new(super::super::source::ParseError::InvalidDate("x".into())),
+        ) else {
+            panic!("A parse error should be recoverable");
+        };
+    }
+}
@@ -0,0 +1,568 @@
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Sqlite};
+
+use super::{
+    by_class,
+    retry::ScrapeError,
+    scope::{has_set_code, Scope},
+    Expansion, Pokemon,
+};
+
+/// Name recorded against cards that no rule applies to.
+pub const DEFAULT_RULE: &str = "default";
+
+/// Parses an interval such as `45s`, `30m`, `6h`, `7d` or `2w`.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let unit = s
+        .char_indices()
+        .last()
+        .map(|(i, _)| i)
+        .ok_or("Empty interval")?;
+    let (amount, unit) = s.split_at(unit);
+    let amount = amount
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| format!("Invalid interval {s}: {e}"))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid interval {s}, expected a unit of s, m, h, d or w"
+            ))
+        }
+    };
+
+    match amount.checked_mul(seconds) {
+        Some(0) => Err(format!("Invalid interval {s}, must be more than zero")),
+        Some(x) => Ok(Duration::from_secs(x)),
+        None => Err(format!("Invalid interval {s}, interval too large")),
+    }
+}
+
+/// A rule as written in the schedule file.
+///
+/// A rule applies to every card unless scoped by expansion, release date or a [`Scope`], and scopes
+/// combine.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    interval: String,
+    #[serde(default)]
+    priority: i32,
+    expansion: Option<String>,
+    released_within: Option<String>,
+    #[serde(flatten)]
+    scope: Scope,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefaultConfig {
+    interval: String,
+    #[serde(default)]
+    priority: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    default: DefaultConfig,
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    interval: Duration,
+    priority: i32,
+    expansion: Option<String>,
+    released_within: Option<Duration>,
+    scope: Scope,
+}
+
+impl Rule {
+    fn applies_to(&self, card: &Pokemon, expansion: &Expansion, today: NaiveDate) -> bool {
+        self.expansion
+            .as_deref()
+            .is_none_or(|x| has_set_code(expansion, x))
+            && self.scope.applies_to(card, expansion)
+            && self.released_within.is_none_or(|within| {
+                // INFO: Expansions without a release date are never considered new
+                expansion.release_date.is_some_and(|released| {
+                    chrono::TimeDelta::from_std(within).is_ok_and(|x| today - released <= x)
+                })
+            })
+    }
+}
+
+/// How often a card is scraped and how it is ordered against other due cards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub interval: Duration,
+    /// Higher priorities are scraped first.
+    pub priority: i32,
+    /// The rule the interval came from.
+    pub rule: String,
+}
+
+/// Refresh intervals and priorities for cards, loaded from a TOML file.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    default: Plan,
+    rules: Vec<Rule>,
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file =
+            toml::from_str::<ScheduleFile>(s).map_err(|e| format!("Invalid schedule: {e}"))?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let interval = |x: &str| {
+                    parse_interval(x).map_err(|e| format!("Invalid rule {}: {e}", rule.name))
+                };
+
+                Ok(Rule {
+                    interval: interval(&rule.interval)?,
+                    released_within: rule.released_within.as_deref().map(interval).transpose()?,
+                    priority: rule.priority,
+                    expansion: rule.expansion,
+                    scope: rule.scope,
+                    name: rule.name,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            default: Plan {
+                interval: parse_interval(&file.default.interval)
+                    .map_err(|e| format!("Invalid default: {e}"))?,
+                priority: file.default.priority,
+                rule: DEFAULT_RULE.into(),
+            },
+            rules,
+        })
+    }
+}
+
+impl Schedule {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read schedule {}: {e}", path.display()))?
+            .parse()
+    }
+
+    /// Plans a card with a single class.
+    ///
+    /// Every rule that applies asks for the card to be scraped at least that often, so the
+    /// shortest interval and the highest priority win. The default only applies when no rule
+    /// does.
+    pub fn plan(&self, card: &Pokemon, expansion: &Expansion, today: NaiveDate) -> Plan {
+        let rules = self
+            .rules
+            .iter()
+            .filter(|x| x.applies_to(card, expansion, today))
+            .collect::<Vec<_>>();
+
+        // INFO: `min_by_key` keeps the first of equal intervals, so ties go to file order
+        let Some(shortest) = rules.iter().min_by_key(|x| x.interval) else {
+            return self.default.clone();
+        };
+
+        Plan {
+            interval: shortest.interval,
+            priority: rules.iter().map(|x| x.priority).max().unwrap_or_default(),
+            rule: shortest.name.clone(),
+        }
+    }
+}
+
+/// A card whose next scrape is due.
+#[derive(Debug, Clone, FromRow)]
+pub struct DueCard {
+    #[sqlx(rename = "card_expansion_id")]
+    pub expansion_id: u32,
+    #[sqlx(rename = "card_number")]
+    pub number: u32,
+    #[sqlx(rename = "card_class")]
+    pub class: String,
+    pub interval_seconds: i64,
+}
+
+impl DueCard {
+    /// Finds the card with just the due class among the loaded expansions.
+    pub fn find<'a>(&self, expansions: &'a [Expansion]) -> Option<(&'a Expansion, Pokemon)> {
+        let expansion = expansions.iter().find(|x| x.id == self.expansion_id)?;
+        let card = expansion
+            .cards
+            .iter()
+            .find(|x| x.number == self.number as usize)?;
+        let class = card
+            .class
+            .iter()
+            .find(|x| x.to_string() == self.class)?
+            .clone();
+
+        Some((
+            expansion,
+            Pokemon {
+                class: vec![class],
+                ..card.clone()
+            },
+        ))
+    }
+}
+
+/// Counts of the scheduled cards in enabled expansions.
+#[derive(Debug, Clone, Default, FromRow, Serialize)]
+pub struct ScheduleSummary {
+    pub scheduled: u32,
+    pub due: u32,
+    pub next_due_at: Option<DateTime<Utc>>,
+}
+
+/// Brings the stored schedule in line with the current rules and expansions.
+///
+/// Cards are due again an interval after they were last attempted, so changing a rule reschedules
+/// cards that have already been scraped. Cards that have never been attempted are due now, and
+/// cards that are no longer in `expansions` are removed from the schedule.
+pub async fn sync(
+    pool: &sqlx::Pool<Sqlite>,
+    expansions: &[Expansion],
+    schedule: &Schedule,
+    now: DateTime<Utc>,
+) -> Result<(), ScrapeError> {
+    let mut attempted = sqlx::query_as::<_, (u32, u32, String, Option<DateTime<Utc>>)>(
+        "
+        SELECT card_expansion_id, card_number, card_class, last_attempt_at
+        FROM scrape_schedule",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ScrapeError::database("Failed to get scrape schedule", e))?
+    .into_iter()
+    .map(|(expansion_id, number, class, at)| ((expansion_id, number, class), at))
+    .collect::<std::collections::HashMap<_, _>>();
+
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| ScrapeError::database("Failed to start transaction", e))?;
+
+    for expansion in expansions {
+        for card in by_class(&expansion.cards) {
+            let class = card.class.first().unwrap().to_string();
+            let plan = schedule.plan(&card, expansion, now.date_naive());
+            let next_due_at = attempted
+                .remove(&(expansion.id, card.number as u32, class.clone()))
+                .flatten()
+                .map_or(now, |at| at + plan.interval);
+
+            sqlx::query(
+                "
+                INSERT INTO scrape_schedule
+                    (card_expansion_id, card_number, card_class, interval_seconds, priority, rule, next_due_at)
+                VALUES
+                    (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (card_expansion_id, card_number, card_class) DO UPDATE SET
+                    interval_seconds = excluded.interval_seconds,
+                    priority = excluded.priority,
+                    rule = excluded.rule,
+                    next_due_at = excluded.next_due_at",
+            )
+            .bind(expansion.id)
+            .bind(card.number as u32)
+            .bind(&class)
+            .bind(plan.interval.as_secs() as i64)
+            .bind(plan.priority)
+            .bind(&plan.rule)
+            .bind(next_due_at)
+            .execute(&mut *txn)
+            .await
+            .map_err(|e| ScrapeError::database("Failed to schedule card", e))?;
+        }
+    }
+
+    // INFO: Whatever is left wasn't in the loaded expansions, i.e. a re-registered manifest dropped
+    // the card or changed its class, and would otherwise stay due forever
+    for (expansion_id, number, class) in attempted.into_keys() {
+        sqlx::query(
+            "
+            DELETE FROM scrape_schedule
+            WHERE card_expansion_id = ? AND card_number = ? AND card_class = ?",
+        )
+        .bind(expansion_id)
+        .bind(number)
+        .bind(class)
+        .execute(&mut *txn)
+        .await
+        .map_err(|e| ScrapeError::database("Failed to unschedule card", e))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|e| ScrapeError::database("Failed to commit scrape schedule", e))
+}
+
+/// Gets up to `limit` due cards of enabled expansions, highest priority and most overdue first.
+pub async fn due(
+    pool: &sqlx::Pool<Sqlite>,
+    now: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<DueCard>, sqlx::Error> {
+    sqlx::query_as::<_, DueCard>(
+        "
+        SELECT card_expansion_id, card_number, card_class, interval_seconds
+        FROM scrape_schedule
+        JOIN expansions ON expansions.id = scrape_schedule.card_expansion_id
+        WHERE expansions.enabled AND next_due_at <= ?
+        ORDER BY priority DESC, next_due_at, card_expansion_id, card_number, card_class
+        LIMIT ?",
+    )
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Records an attempt at a due card, successful or not, and schedules its next scrape.
+pub async fn mark_attempted(
+    pool: &sqlx::Pool<Sqlite>,
+    card: &DueCard,
+    at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        UPDATE scrape_schedule
+        SET last_attempt_at = ?, next_due_at = ?
+        WHERE card_expansion_id = ? AND card_number = ? AND card_class = ?",
+    )
+    .bind(at)
+    .bind(at + Duration::from_secs(card.interval_seconds as u64))
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(&card.class)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a due card that's no longer in the loaded expansions from the schedule.
+pub async fn remove(pool: &sqlx::Pool<Sqlite>, card: &DueCard) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        DELETE FROM scrape_schedule
+        WHERE card_expansion_id = ? AND card_number = ? AND card_class = ?",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(&card.class)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn summary(
+    pool: &sqlx::Pool<Sqlite>,
+    now: DateTime<Utc>,
+) -> Result<ScheduleSummary, sqlx::Error> {
+    sqlx::query_as::<_, ScheduleSummary>(
+        "
+        SELECT
+            COUNT(*) AS scheduled,
+            COALESCE(SUM(next_due_at <= ?), 0) AS due,
+            MIN(next_due_at) AS next_due_at
+        FROM scrape_schedule
+        JOIN expansions ON expansions.id = scrape_schedule.card_expansion_id
+        WHERE expansions.enabled",
+    )
+    .bind(now)
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_scraper::{
+        testing::{self, expansion},
+        Class, Rarity,
+    };
+
+    fn card(number: usize, rarity: Rarity) -> Pokemon {
+        testing::card("Pikachu", number, rarity, Class::Regular)
+    }
+
+    const SCHEDULE: &str = r#"
+        [default]
+        interval = "3d"
+
+        [[rule]]
+        name = "new_expansions"
+        released_within = "90d"
+        interval = "1d"
+        priority = 10
+
+        [[rule]]
+        name = "special_illustration_rares"
+        rarity = "Special Illustration Rare"
+        interval = "6h"
+        priority = 20
+
+        [[rule]]
+        name = "commons"
+        rarity = "Common"
+        interval = "1w"
+
+        [[rule]]
+        name = "tef_051"
+        card = { expansion = "TEF", number = 51 }
+        interval = "30m"
+    "#;
+
+    fn plan(card: &Pokemon, released: &str) -> Plan {
+        let Ok(schedule) = SCHEDULE.parse::<Schedule>() else {
+            panic!("Failed to parse schedule");
+        };
+        let Ok(today) = NaiveDate::parse_from_str("2024-06-01", "%Y-%m-%d") else {
+            panic!("Failed to parse date");
+        };
+        let released = NaiveDate::parse_from_str(released, "%Y-%m-%d").ok();
+
+        schedule.plan(card, &expansion(released), today)
+    }
+
+    #[test]
+    fn it_parses_intervals() {
+        assert_eq!(parse_interval("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_interval("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_interval("6h"), Ok(Duration::from_secs(6 * 60 * 60)));
+        assert_eq!(parse_interval("7d"), parse_interval("1w"));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("6").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn it_uses_the_default_when_no_rule_applies() {
+        let plan = plan(&card(1, Rarity::Uncommon), "2020-01-01");
+        assert_eq!(plan.rule, DEFAULT_RULE);
+        assert_eq!(plan.interval, Duration::from_secs(3 * 24 * 60 * 60));
+        assert_eq!(plan.priority, 0);
+    }
+
+    #[test]
+    fn it_uses_the_shortest_interval_and_highest_priority() {
+        let sir = plan(&card(1, Rarity::SpecialIllustrationRare), "2024-05-01");
+        assert_eq!(sir.rule, "special_illustration_rares");
+        assert_eq!(sir.priority, 20);
+
+        let new_common = plan(&card(1, Rarity::Common), "2024-05-01");
+        assert_eq!(new_common.rule, "new_expansions");
+        assert_eq!(new_common.priority, 10);
+
+        let old_common = plan(&card(1, Rarity::Common), "2020-01-01");
+        assert_eq!(old_common.rule, "commons");
+        assert_eq!(old_common.interval, Duration::from_secs(7 * 24 * 60 * 60));
+
+        let single_card = plan(&card(51, Rarity::Common), "2020-01-01");
+        assert_eq!(single_card.rule, "tef_051");
+    }
+
+    #[test]
+    fn it_rejects_unknown_fields() {
+        assert!(r#"
+            [default]
+            interval = "1d"
+
+            [[rule]]
+            name = "typo"
+            interval = "1d"
+            raritty = "Common"
+        "#
+        .parse::<Schedule>()
+        .is_err());
+    }
+
+    async fn pool() -> sqlx::Pool<Sqlite> {
+        // INFO: Every connection to an in-memory database gets its own, so there can only be one
+        let Ok(pool) = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+        else {
+            panic!("Failed to open database");
+        };
+        if let Err(e) = sqlx::migrate!("db/migrations").run(&pool).await {
+            panic!("Failed to migrate database: {e}");
+        }
+
+        pool
+    }
+
+    #[tokio::test]
+    async fn it_removes_cards_that_are_no_longer_in_the_expansions() {
+        let pool = pool().await;
+        let now = Utc::now();
+        let Ok(schedule) = SCHEDULE.parse::<Schedule>() else {
+            panic!("Failed to parse schedule");
+        };
+
+        let Ok(_) = sqlx::query(
+            "
+            INSERT INTO expansions (id, set_name, expansion_name, series_number, set_code)
+            VALUES (1, 'Scarlet & Violet', 'Temporal Forces', 5, 'TEF');
+            INSERT INTO cards (expansion_id, number, class, name, rarity)
+            VALUES (1, 1, 'Regular', 'Pikachu', 'Common'), (1, 2, 'Regular', 'Raichu', 'Common');
+            INSERT INTO scrape_schedule
+                (card_expansion_id, card_number, card_class, interval_seconds, priority, rule, next_due_at)
+            VALUES (1, 2, 'Regular', 60, 0, 'default', ?);
+            ",
+        )
+        .bind(now)
+        .execute(&pool)
+        .await
+        else {
+            panic!("Failed to insert stale card");
+        };
+
+        let expansions = [Expansion {
+            cards: vec![card(1, Rarity::Common)],
+            ..expansion(None)
+        }];
+        if let Err(e) = sync(&pool, &expansions, &schedule, now).await {
+            panic!("Failed to sync schedule: {e}");
+        }
+
+        let Ok(due) = due(&pool, now, 10).await else {
+            panic!("Failed to get due cards");
+        };
+        assert_eq!(
+            due.iter().map(|x| x.number).collect::<Vec<_>>(),
+            vec![1],
+            "Only the card still in the expansion should be due"
+        );
+    }
+}
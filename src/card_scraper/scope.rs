@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+use super::{Expansion, Pokemon};
+
+/// Restricts a rule to one card, identified by its expansion's set code and number.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct CardScope {
+    pub expansion: String,
+    pub number: usize,
+}
+
+/// Which cards a title or schedule rule applies to.
+///
+/// A rule applies to every card unless scoped by rarity, class or card, and scopes combine.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Scope {
+    rarity: Option<String>,
+    class: Option<String>,
+    card: Option<CardScope>,
+}
+
+impl Scope {
+    pub fn applies_to(&self, card: &Pokemon, expansion: &Expansion) -> bool {
+        self.rarity
+            .as_ref()
+            .is_none_or(|x| x.eq_ignore_ascii_case(&card.rarity.to_string()))
+            && self.class.as_ref().is_none_or(|x| {
+                card.class
+                    .first()
+                    .is_some_and(|class| x.eq_ignore_ascii_case(&class.to_string()))
+            })
+            && self
+                .card
+                .as_ref()
+                .is_none_or(|x| x.number == card.number && has_set_code(expansion, &x.expansion))
+    }
+}
+
+/// Whether the expansion's set code is `set_code`, ignoring case.
+pub fn has_set_code(expansion: &Expansion, set_code: &str) -> bool {
+    expansion
+        .set_code
+        .as_ref()
+        .is_some_and(|x| set_code.eq_ignore_ascii_case(x))
+}
@@ -0,0 +1,68 @@
+use super::{MarketplaceSource, ResultsPage};
+use crate::{
+    card_scraper::{ActiveListing, Expansion, Listing, Pokemon, ScrapeMode},
+    currency::{Currency, GBP},
+};
+
+mod parser;
+mod search_url;
+
+use search_url::{SearchUrl, CCG_INDIVIDUAL_CARDS, MAX_ITEMS_PER_PAGE};
+
+pub const EBAY_UK_ID: &str = "ebay_uk";
+
+#[derive(Debug)]
+pub struct Ebay {
+    id: &'static str,
+    base_url: &'static str,
+    /// Currency that prices are shown in.
+    currency: &'static Currency,
+}
+
+impl Ebay {
+    pub fn uk() -> Self {
+        Self {
+            id: EBAY_UK_ID,
+            base_url: "https://www.ebay.co.uk",
+            currency: GBP,
+        }
+    }
+}
+
+impl MarketplaceSource for Ebay {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn search_url(
+        &self,
+        card: &Pokemon,
+        expansion: &Expansion,
+        mode: ScrapeMode,
+    ) -> Result<String, url::ParseError> {
+        let url = SearchUrl::new(
+            self.base_url,
+            format!(
+                "{} {:0>3}/{}",
+                card.name, card.number, expansion.expansion_total
+            ),
+        )
+        .items_per_page(MAX_ITEMS_PER_PAGE)
+        .category(CCG_INDIVIDUAL_CARDS);
+
+        match mode {
+            ScrapeMode::Sold => url.sold(),
+            ScrapeMode::Active => url,
+        }
+        .build()
+        .map(String::from)
+    }
+
+    fn parse_sold_page(&self, html: &str) -> ResultsPage<Listing> {
+        parser::parse_sold_page(html, self.id, self.currency)
+    }
+
+    fn parse_active_page(&self, html: &str) -> ResultsPage<ActiveListing> {
+        parser::parse_active_page(html, self.id, self.currency)
+    }
+}
@@ -0,0 +1,490 @@
+use chrono::NaiveDate;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::card_scraper::{
+    source::{ParseError, ResultsPage},
+    ActiveListing, BuyingFormat, Listing,
+};
+use crate::currency::{
+    parse::{self, Price},
+    Currency, Money,
+};
+
+const END_OF_RESULTS_MARKER: &str = "Results matching fewer words";
+
+struct Selectors {
+    results: Selector,
+    caption: Selector,
+    title: Selector,
+    price: Selector,
+    attributes: Selector,
+    link: Selector,
+    buying_format: Selector,
+    offer_accepted: Selector,
+    next_page: Selector,
+}
+
+impl Selectors {
+    fn new() -> Self {
+        let parse = |s| Selector::parse(s).expect("Selectors should be valid");
+        Self {
+            results: parse("ul.srp-results > li"),
+            caption: parse(".s-card__caption"),
+            title: parse("a > div.s-card__title span"),
+            price: parse(".s-card__price"),
+            attributes: parse(".su-card-container__attributes__primary .s-card__attribute-row"),
+            link: parse(".su-card-container__header a"),
+            buying_format: parse(
+                ".su-card-container__attributes__primary .s-card__attribute-row:nth-child(2)",
+            ),
+            offer_accepted: parse(
+                ".su-card-container__attributes__primary .s-card__attribute-row:nth-child(3)",
+            ),
+            next_page: parse("a.pagination__next"),
+        }
+    }
+}
+
+/// Parses an eBay sold listings search results document.
+///
+/// Parsing stops at the "Results matching fewer words" divider, after which eBay only shows loose
+/// matches, and no next page is returned in that case.
+pub fn parse_sold_page(
+    html: &str,
+    source: &'static str,
+    currency: &'static Currency,
+) -> ResultsPage<Listing> {
+    parse_page(html, |item, selectors| {
+        parse_sold_listing(item, selectors, source, currency)
+    })
+}
+
+/// Parses an eBay search results document of listings that are still for sale.
+pub fn parse_active_page(
+    html: &str,
+    source: &'static str,
+    currency: &'static Currency,
+) -> ResultsPage<ActiveListing> {
+    parse_page(html, |item, selectors| {
+        parse_active_listing(item, selectors, source, currency)
+    })
+}
+
+fn parse_page<T>(
+    html: &str,
+    parse_listing: impl Fn(ElementRef<'_>, &Selectors) -> Result<T, ParseError>,
+) -> ResultsPage<T> {
+    let document = Html::parse_document(html);
+    let selectors = Selectors::new();
+
+    let mut listings = Vec::new();
+    let mut reached_end = false;
+
+    for item in document.select(&selectors.results) {
+        if !item.value().classes().any(|x| x == "s-card") {
+            if text(item) == END_OF_RESULTS_MARKER {
+                reached_end = true;
+                break;
+            }
+
+            continue;
+        }
+
+        listings.push(parse_listing(item, &selectors));
+    }
+
+    let next_page = if reached_end {
+        None
+    } else {
+        document
+            .select(&selectors.next_page)
+            .next()
+            .and_then(|x| x.value().attr("href"))
+            .map(str::to_string)
+    };
+
+    ResultsPage {
+        listings,
+        next_page,
+    }
+}
+
+fn parse_sold_listing(
+    item: ElementRef<'_>,
+    selectors: &Selectors,
+    source: &'static str,
+    currency: &'static Currency,
+) -> Result<Listing, ParseError> {
+    let caption = find_text(item, &selectors.caption, ".s-card__caption")?;
+    let date = NaiveDate::parse_from_str(caption.trim_start_matches("Sold "), "%-d %b %Y")
+        .map_err(|_| ParseError::InvalidDate(caption.clone()))?;
+
+    let listing = parse_active_listing(item, selectors, source, currency)?;
+
+    Ok(Listing {
+        id: listing.id,
+        source: listing.source,
+        title: listing.title,
+        date,
+        price: listing.price,
+        postage: listing.postage,
+        link: listing.link,
+        buying_format: listing.buying_format,
+        grade: listing.grade,
+        flags: listing.flags,
+    })
+}
+
+fn parse_active_listing(
+    item: ElementRef<'_>,
+    selectors: &Selectors,
+    source: &'static str,
+    currency: &'static Currency,
+) -> Result<ActiveListing, ParseError> {
+    let title = find_text(item, &selectors.title, "a > div.s-card__title span")?;
+
+    let link = item
+        .select(&selectors.link)
+        .next()
+        .ok_or(ParseError::MissingElement(".su-card-container__header a"))?
+        .value()
+        .attr("href")
+        .unwrap_or_default();
+
+    let link = link
+        .split("?")
+        .next()
+        .expect("One result should always be returned")
+        .to_string();
+
+    let raw_price = find_text(item, &selectors.price, ".s-card__price")?;
+    let (price, postage) = match parse::parse(&raw_price, currency) {
+        Ok(Price::Single(price)) => (price, parse_postage(item, selectors, price.currency())),
+        Ok(Price::WithPostage { price, postage }) => (price, Some(postage)),
+        Ok(Price::Range { .. }) => {
+            return Err(ParseError::PriceRange {
+                price: raw_price,
+                title,
+                link,
+            })
+        }
+        Err(error) => {
+            return Err(ParseError::InvalidPrice {
+                price: raw_price,
+                title,
+                link,
+                error,
+            })
+        }
+    };
+
+    let id = link
+        .split("/")
+        .last()
+        .expect("Split always returns one value")
+        .parse()
+        .map_err(|_| ParseError::InvalidId(link.clone()))?;
+
+    let buying_format = match find_text(
+        item,
+        &selectors.buying_format,
+        ".s-card__attribute-row:nth-child(2)",
+    )?
+    .as_str()
+    {
+        "Buy It Now" => BuyingFormat::BuyItNow {
+            accepts_offers: false,
+            offer_was_accepted: false,
+        },
+        "or Best Offer" => BuyingFormat::BuyItNow {
+            accepts_offers: true,
+            offer_was_accepted: false,
+        },
+        "Best Offer accepted" => BuyingFormat::BuyItNow {
+            accepts_offers: true,
+            offer_was_accepted: true,
+        },
+        bids => BuyingFormat::Auction {
+            bids: bids
+                .split_whitespace()
+                .next()
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(|| ParseError::InvalidBuyingFormat(bids.to_string()))?,
+            offer_was_accepted: item
+                .select(&selectors.offer_accepted)
+                .next()
+                .map(text)
+                .is_some_and(|x| x == "Best Offer accepted"),
+        },
+    };
+
+    Ok(ActiveListing {
+        id,
+        source,
+        title,
+        price,
+        postage,
+        link,
+        buying_format,
+        grade: None,
+        flags: vec![],
+    })
+}
+
+/// Finds the postage shown below the price, which is `None` if it's not specified or can't be
+/// parsed.
+fn parse_postage(
+    item: ElementRef<'_>,
+    selectors: &Selectors,
+    currency: &'static Currency,
+) -> Option<Money> {
+    let postage = item.select(&selectors.attributes).map(text).find(|x| {
+        let x = x.to_lowercase();
+        ["postage", "delivery", "shipping"]
+            .iter()
+            .any(|word| x.contains(word))
+    })?;
+
+    if postage.to_lowercase().contains("not specified") {
+        return None;
+    }
+
+    parse::parse_postage(&postage, currency)
+        .inspect_err(|e| tracing::warn!(postage, error = %e, "Failed to parse postage"))
+        .ok()
+}
+
+fn find_text(
+    item: ElementRef<'_>,
+    selector: &Selector,
+    name: &'static str,
+) -> Result<String, ParseError> {
+    item.select(selector)
+        .next()
+        .map(text)
+        .ok_or(ParseError::MissingElement(name))
+}
+
+/// Collapses the element text the way a browser would render it.
+fn text(element: ElementRef<'_>) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{card_scraper::source::ebay::EBAY_UK_ID, currency::GBP};
+
+    const SOLD_LISTINGS: &str = include_str!("../../../../fixtures/ebay/sold_listings.html");
+    const LAST_PAGE: &str = include_str!("../../../../fixtures/ebay/sold_listings_last_page.html");
+    const ACTIVE_LISTINGS: &str = include_str!("../../../../fixtures/ebay/active_listings.html");
+    const NO_RESULTS: &str = include_str!("../../../../fixtures/ebay/no_results.html");
+    const UNPARSEABLE: &str =
+        include_str!("../../../../fixtures/ebay/sold_listings_unparseable.html");
+    const POSTAGE: &str = include_str!("../../../../fixtures/ebay/sold_listings_postage.html");
+
+    #[test]
+    fn it_parses_every_card_on_the_page() {
+        let page = parse_sold_page(SOLD_LISTINGS, EBAY_UK_ID, GBP);
+
+        assert_eq!(page.listings.len(), 6);
+        assert_eq!(
+            page.next_page.as_deref(),
+            Some("https://www.ebay.co.uk/sch/i.html?_nkw=charizard+ex+125%2F197&_sacat=0&LH_Sold=1&_pgn=2")
+        );
+    }
+
+    #[test]
+    fn it_parses_a_buy_it_now_listing() {
+        let page = parse_sold_page(SOLD_LISTINGS, EBAY_UK_ID, GBP);
+
+        let Some(Ok(listing)) = page.listings.first() else {
+            panic!("Failed to parse listing");
+        };
+
+        assert_eq!(listing.id, 226512345678);
+        assert_eq!(listing.source, EBAY_UK_ID);
+        assert_eq!(
+            listing.title,
+            "Charizard ex 125/197 Obsidian Flames Double Rare Pokemon Card"
+        );
+        assert_eq!(listing.date, NaiveDate::from_ymd_opt(2025, 1, 12).unwrap());
+        assert_eq!(listing.price.to_string(), "£4.99");
+        assert_eq!(listing.link, "https://www.ebay.co.uk/itm/226512345678");
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::BuyItNow {
+                accepts_offers: false,
+                offer_was_accepted: false
+            }
+        ));
+        assert!(listing.grade.is_none());
+    }
+
+    #[test]
+    fn it_parses_best_offer_listings() {
+        let page = parse_sold_page(SOLD_LISTINGS, EBAY_UK_ID, GBP);
+
+        let Some(Ok(listing)) = page.listings.get(1) else {
+            panic!("Failed to parse listing");
+        };
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::BuyItNow {
+                accepts_offers: true,
+                offer_was_accepted: false
+            }
+        ));
+
+        let Some(Ok(listing)) = page.listings.get(2) else {
+            panic!("Failed to parse listing");
+        };
+        assert_eq!(listing.price.to_string(), "£1050.00");
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::BuyItNow {
+                accepts_offers: true,
+                offer_was_accepted: true
+            }
+        ));
+    }
+
+    #[test]
+    fn it_parses_auction_listings() {
+        let page = parse_sold_page(SOLD_LISTINGS, EBAY_UK_ID, GBP);
+
+        let Some(Ok(listing)) = page.listings.get(3) else {
+            panic!("Failed to parse listing");
+        };
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::Auction {
+                bids: 12,
+                offer_was_accepted: false
+            }
+        ));
+
+        let Some(Ok(listing)) = page.listings.get(4) else {
+            panic!("Failed to parse listing");
+        };
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::Auction {
+                bids: 1,
+                offer_was_accepted: true
+            }
+        ));
+    }
+
+    #[test]
+    fn it_parses_postage() {
+        let page = parse_sold_page(POSTAGE, EBAY_UK_ID, GBP);
+
+        let postage = page
+            .listings
+            .iter()
+            .map(|x| x.as_ref().map(|x| x.postage.map(|x| x.to_string())))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            postage,
+            vec![Ok(Some("£1.55".into())), Ok(Some("£0.00".into())), Ok(None),]
+        );
+    }
+
+    #[test]
+    fn it_returns_an_error_for_range_prices() {
+        let page = parse_sold_page(SOLD_LISTINGS, EBAY_UK_ID, GBP);
+
+        let Some(Err(err)) = page.listings.get(5) else {
+            panic!("Range price should not parse");
+        };
+
+        assert!(matches!(
+            err,
+            ParseError::PriceRange { price, link, .. }
+                if price == "£5.00 to £12.00" && link.starts_with("https://www.ebay.co.uk/itm/")
+        ));
+    }
+
+    #[test]
+    fn it_keeps_parsing_after_an_unrecognised_buying_format() {
+        let page = parse_sold_page(UNPARSEABLE, EBAY_UK_ID, GBP);
+
+        assert!(matches!(
+            page.listings.first(),
+            Some(Err(ParseError::InvalidBuyingFormat(format))) if format == "Classified Ad"
+        ));
+        let Some(Ok(listing)) = page.listings.get(1) else {
+            panic!("Failed to parse listing after an unparseable one");
+        };
+        assert_eq!(listing.id, 226512345702);
+    }
+
+    #[test]
+    fn it_stops_at_the_fewer_words_divider() {
+        let page = parse_sold_page(LAST_PAGE, EBAY_UK_ID, GBP);
+
+        assert_eq!(page.listings.len(), 2);
+        assert!(page.listings.iter().all(|x| x.is_ok()));
+        assert!(page.next_page.is_none());
+    }
+
+    #[test]
+    fn it_parses_active_listings() {
+        let page = parse_active_page(ACTIVE_LISTINGS, EBAY_UK_ID, GBP);
+
+        assert_eq!(page.listings.len(), 3);
+        assert!(page.next_page.is_none());
+
+        let Some(Ok(listing)) = page.listings.first() else {
+            panic!("Failed to parse listing");
+        };
+        assert_eq!(listing.id, 226598765432);
+        assert_eq!(listing.price.to_string(), "£4.25");
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::BuyItNow {
+                accepts_offers: true,
+                offer_was_accepted: false
+            }
+        ));
+
+        let Some(Ok(listing)) = page.listings.get(1) else {
+            panic!("Failed to parse listing");
+        };
+        assert!(matches!(
+            listing.buying_format,
+            BuyingFormat::Auction {
+                bids: 0,
+                offer_was_accepted: false
+            }
+        ));
+    }
+
+    #[test]
+    fn it_requires_a_sold_date_for_sold_listings() {
+        let page = parse_sold_page(ACTIVE_LISTINGS, EBAY_UK_ID, GBP);
+
+        assert!(page
+            .listings
+            .iter()
+            .all(|x| matches!(x, Err(ParseError::MissingElement(_)))));
+    }
+
+    #[test]
+    fn it_returns_nothing_for_an_empty_search() {
+        let page = parse_sold_page(NO_RESULTS, EBAY_UK_ID, GBP);
+
+        assert!(page.listings.is_empty());
+        assert!(page.next_page.is_none());
+        assert!(parse_active_page(NO_RESULTS, EBAY_UK_ID, GBP)
+            .listings
+            .is_empty());
+    }
+}
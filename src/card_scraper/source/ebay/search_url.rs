@@ -0,0 +1,146 @@
+/// eBay's "CCG Individual Cards" category.
+pub const CCG_INDIVIDUAL_CARDS: u32 = 183454;
+
+/// Largest page size eBay will serve.
+pub const MAX_ITEMS_PER_PAGE: u16 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    BestMatch,
+    /// Most recently ended first. Used for sold listings so that scraping can stop as soon as it
+    /// reaches a listing that is already stored.
+    RecentlyEnded,
+}
+
+impl SortOrder {
+    fn param(&self) -> &'static str {
+        match self {
+            Self::BestMatch => "12",
+            Self::RecentlyEnded => "13",
+        }
+    }
+}
+
+/// Builds an eBay search results URL so that a search can be opened in a single navigation.
+#[derive(Debug, Clone)]
+pub struct SearchUrl {
+    base_url: String,
+    query: String,
+    sold: bool,
+    completed: bool,
+    items_per_page: Option<u16>,
+    category: Option<u32>,
+    sort: SortOrder,
+}
+
+impl SearchUrl {
+    pub fn new(base_url: impl Into<String>, query: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            query: query.into(),
+            sold: false,
+            completed: false,
+            items_per_page: None,
+            category: None,
+            sort: SortOrder::BestMatch,
+        }
+    }
+
+    /// Only return sold and completed listings, most recently ended first.
+    pub fn sold(mut self) -> Self {
+        self.sold = true;
+        self.completed = true;
+        self.sort = SortOrder::RecentlyEnded;
+        self
+    }
+
+    pub fn items_per_page(mut self, items_per_page: u16) -> Self {
+        self.items_per_page = Some(items_per_page);
+        self
+    }
+
+    pub fn category(mut self, category: u32) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn build(&self) -> Result<url::Url, url::ParseError> {
+        let mut url = url::Url::parse(&self.base_url)?.join("/sch/i.html")?;
+
+        {
+            let mut params = url.query_pairs_mut();
+            params
+                .append_pair("_nkw", &self.query)
+                .append_pair("_sacat", &self.category.unwrap_or(0).to_string());
+
+            if self.sold {
+                params.append_pair("LH_Sold", "1");
+            }
+
+            if self.completed {
+                params.append_pair("LH_Complete", "1");
+            }
+
+            if let Some(items_per_page) = self.items_per_page {
+                params.append_pair("_ipg", &items_per_page.to_string());
+            }
+
+            params.append_pair("_sop", self.sort.param());
+        }
+
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn it_builds_a_sold_search() {
+        let Ok(url) = SearchUrl::new("https://www.ebay.co.uk", "Charizard ex 125/197")
+            .sold()
+            .items_per_page(MAX_ITEMS_PER_PAGE)
+            .category(CCG_INDIVIDUAL_CARDS)
+            .build()
+        else {
+            panic!("Failed to build url");
+        };
+
+        assert_eq!(
+            url.as_str(),
+            "https://www.ebay.co.uk/sch/i.html?_nkw=Charizard+ex+125%2F197&_sacat=183454&LH_Sold=1&LH_Complete=1&_ipg=240&_sop=13"
+        );
+    }
+
+    #[test]
+    fn it_builds_an_unfiltered_search() {
+        let Ok(url) = SearchUrl::new("https://www.ebay.co.uk", "Pikachu").build() else {
+            panic!("Failed to build url");
+        };
+
+        assert_eq!(
+            url.as_str(),
+            "https://www.ebay.co.uk/sch/i.html?_nkw=Pikachu&_sacat=0&_sop=12"
+        );
+    }
+
+    #[test]
+    fn it_encodes_special_characters() {
+        let Ok(url) = SearchUrl::new("https://www.ebay.co.uk", "Farfetch'd & Flabébé").build()
+        else {
+            panic!("Failed to build url");
+        };
+
+        assert_eq!(
+            url.query_pairs().next().map(|(_, v)| v.into_owned()),
+            Some("Farfetch'd & Flabébé".to_string())
+        );
+    }
+
+    #[test]
+    fn it_rejects_an_invalid_base_url() {
+        assert!(SearchUrl::new("not a url", "Pikachu").build().is_err());
+    }
+}
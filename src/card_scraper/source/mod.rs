@@ -0,0 +1,101 @@
+use super::{ActiveListing, Expansion, Listing, Pokemon, ScrapeMode};
+use crate::currency::parse::PriceError;
+
+pub mod ebay;
+
+/// A marketplace that listings can be scraped from.
+///
+/// Implementations own everything that is specific to a site: how the search URL is built, how a
+/// results page is parsed and where the next page lives. `CardScaper` only drives the browser
+/// between pages and applies the card filters.
+pub trait MarketplaceSource: std::fmt::Debug + Send + Sync {
+    /// Identifier stored against every listing scraped from this source.
+    fn id(&self) -> &'static str;
+
+    /// URL of the first page of results for the card.
+    fn search_url(
+        &self,
+        card: &Pokemon,
+        expansion: &Expansion,
+        mode: ScrapeMode,
+    ) -> Result<String, url::ParseError>;
+
+    /// Parses the page source of a sold results page.
+    fn parse_sold_page(&self, html: &str) -> ResultsPage<Listing>;
+
+    /// Parses the page source of a results page of listings that are still for sale.
+    fn parse_active_page(&self, html: &str) -> ResultsPage<ActiveListing>;
+}
+
+/// Looks up a source by the identifier it stores against listings.
+pub fn from_id(id: &str) -> Option<Box<dyn MarketplaceSource>> {
+    match id {
+        ebay::EBAY_UK_ID => Some(Box::new(ebay::Ebay::uk())),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingElement(&'static str),
+    InvalidDate(String),
+    /// Keeps the title and link so that the listing can still be reviewed.
+    InvalidPrice {
+        price: String,
+        title: String,
+        link: String,
+        error: PriceError,
+    },
+    /// A listing with variations sold at one of a range of prices, so what it sold for is
+    /// unknown.
+    PriceRange {
+        price: String,
+        title: String,
+        link: String,
+    },
+    InvalidId(String),
+    InvalidBuyingFormat(String),
+}
+
+impl ParseError {
+    /// Short name of the kind of failure, used to label metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MissingElement(_) => "missing_element",
+            Self::InvalidDate(_) => "invalid_date",
+            Self::InvalidPrice { .. } => "invalid_price",
+            Self::PriceRange { .. } => "price_range",
+            Self::InvalidId(_) => "invalid_id",
+            Self::InvalidBuyingFormat(_) => "invalid_buying_format",
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingElement(selector) => write!(f, "Couldn't find element {selector}"),
+            Self::InvalidDate(date) => write!(f, "Failed to parse date {date}"),
+            Self::InvalidPrice { price, error, .. } => {
+                write!(f, "Failed to parse price {price}: {error}")
+            }
+            Self::PriceRange { price, .. } => write!(f, "Price {price} is a range"),
+            Self::InvalidId(link) => write!(f, "Failed to parse listing id from {link}"),
+            Self::InvalidBuyingFormat(format) => {
+                write!(f, "Failed to parse buying format {format}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A single page of search results.
+///
+/// Listings that fail to parse are kept as errors so that the caller can decide whether to skip
+/// them or give up on the page.
+#[derive(Debug)]
+pub struct ResultsPage<T> {
+    pub listings: Vec<Result<T, ParseError>>,
+    pub next_page: Option<String>,
+}
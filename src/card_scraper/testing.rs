@@ -0,0 +1,34 @@
+use chrono::NaiveDate;
+
+use super::{
+    card_details::{CardDetails, EnergyCard},
+    Class, Expansion, Pokemon, Rarity,
+};
+
+/// A card with a single class and no details worth checking.
+pub fn card(name: &str, number: usize, rarity: Rarity, class: Class) -> Pokemon {
+    Pokemon {
+        name: name.into(),
+        number,
+        rarity,
+        class: vec![class],
+        image_url: String::new(),
+        regulation_mark: None,
+        illustrator: None,
+        details: CardDetails::Energy(EnergyCard::Basic),
+    }
+}
+
+/// Temporal Forces, with set code TEF and no cards.
+pub fn expansion(release_date: Option<NaiveDate>) -> Expansion {
+    Expansion {
+        id: 1,
+        set_name: "Scarlet & Violet".into(),
+        expansion_name: "Temporal Forces".into(),
+        expansion_number: 5.0,
+        expansion_total: 162,
+        set_code: Some("TEF".into()),
+        release_date,
+        cards: vec![],
+    }
+}
@@ -0,0 +1,336 @@
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use super::{scope::Scope, Expansion, Pokemon};
+
+/// Name of the built in rule that rejects titles not mentioning the card.
+pub const CARD_NAME_RULE: &str = "card_name";
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    #[default]
+    Reject,
+    /// Keeps the listing but records that the rule fired, for manual review.
+    Flag,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// Fires when any pattern matches the title.
+    Exclude,
+    /// Fires when no pattern matches the title.
+    Include,
+}
+
+/// A rule as written in the rules file.
+///
+/// A rule applies to the cards in its [`Scope`]. Patterns match whole words case-insensitively unless `regex` is set.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    kind: Kind,
+    patterns: Vec<String>,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    action: Action,
+    #[serde(flatten)]
+    scope: Scope,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub kind: Kind,
+    pub action: Action,
+    scope: Scope,
+    patterns: Vec<Regex>,
+}
+
+impl Rule {
+    fn fires(&self, title: &str) -> bool {
+        let matches = self.patterns.iter().any(|x| x.is_match(title));
+
+        match self.kind {
+            Kind::Exclude => matches,
+            Kind::Include => !matches,
+        }
+    }
+}
+
+/// Outcome of running every applicable rule against a title.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Classification {
+    /// The first rejecting rule that fired.
+    pub rejected_by: Option<String>,
+    /// Every flagging rule that fired.
+    pub flagged_by: Vec<String>,
+}
+
+/// Declarative include and exclude rules for listing titles, loaded from a TOML file.
+#[derive(Debug, Clone, Default)]
+pub struct TitleRules {
+    rules: Vec<Rule>,
+}
+
+fn compile(pattern: &str, regex: bool) -> Result<Regex, regex::Error> {
+    let pattern = if regex {
+        pattern.to_string()
+    } else {
+        format!(
+            r"\b{}\b",
+            pattern
+                .split_whitespace()
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(r"\s+")
+        )
+    };
+
+    RegexBuilder::new(&pattern).case_insensitive(true).build()
+}
+
+impl std::str::FromStr for TitleRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file = toml::from_str::<RulesFile>(s).map_err(|e| format!("Invalid rules: {e}"))?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let patterns = rule
+                    .patterns
+                    .iter()
+                    .map(|x| compile(x, rule.regex))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Invalid pattern in rule {}: {e}", rule.name))?;
+
+                Ok(Rule {
+                    name: rule.name,
+                    kind: rule.kind,
+                    action: rule.action,
+                    scope: rule.scope,
+                    patterns,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self { rules })
+    }
+}
+
+impl TitleRules {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read title rules {}: {e}", path.display()))?
+            .parse()
+    }
+
+    /// Runs the card name check and then every rule that applies to the card, in file order.
+    pub fn classify(&self, card: &Pokemon, expansion: &Expansion, title: &str) -> Classification {
+        let mut classification = Classification::default();
+
+        if !contains_card_name(card, title) {
+            classification.rejected_by = Some(CARD_NAME_RULE.into());
+            return classification;
+        }
+
+        for rule in self
+            .rules
+            .iter()
+            .filter(|x| x.scope.applies_to(card, expansion) && x.fires(title))
+        {
+            match rule.action {
+                Action::Reject => {
+                    classification.rejected_by = Some(rule.name.clone());
+                    break;
+                }
+                Action::Flag => classification.flagged_by.push(rule.name.clone()),
+            }
+        }
+
+        classification
+    }
+}
+
+/// Checks the title contains the card name, or failing that every word of it, as titles often
+/// shorten names like "Castform Sunny Form".
+fn contains_card_name(card: &Pokemon, title: &str) -> bool {
+    let lower_case_title = title.to_lowercase();
+    let name = card.name.to_lowercase();
+
+    lower_case_title.contains(&name)
+        || name
+            .split_whitespace()
+            .all(|x| lower_case_title.contains(x))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::card_scraper::{
+        testing::{card, expansion},
+        Class, Rarity,
+    };
+
+    const RULES: &str = r#"
+        [[rule]]
+        name = "global_sealed"
+        kind = "exclude"
+        patterns = ["master set", "booster box"]
+
+        [[rule]]
+        name = "regular_reverse"
+        kind = "exclude"
+        class = "Regular"
+        patterns = ["reverse"]
+
+        [[rule]]
+        name = "reverse_holo_whitelist"
+        kind = "include"
+        class = "Reverse Holo"
+        patterns = ["reverse", "holo"]
+
+        [[rule]]
+        name = "common_japanese"
+        kind = "exclude"
+        rarity = "Common"
+        action = "flag"
+        patterns = ["japanese"]
+
+        [[rule]]
+        name = "tef_051_promo"
+        kind = "exclude"
+        card = { expansion = "TEF", number = 51 }
+        regex = true
+        patterns = ['\bpromo\b|\bstamped\b']
+    "#;
+
+    fn classify(card: &Pokemon, title: &str) -> Classification {
+        let Ok(rules) = RULES.parse::<TitleRules>() else {
+            panic!("Failed to parse rules");
+        };
+
+        rules.classify(card, &expansion(None), title)
+    }
+
+    #[test]
+    fn it_rejects_titles_without_the_card_name() {
+        let card = card("Pikachu", 51, Rarity::Common, Class::Regular);
+
+        assert_eq!(
+            classify(&card, "Raichu 052/162 Temporal Forces").rejected_by,
+            Some(CARD_NAME_RULE.into())
+        );
+    }
+
+    #[test]
+    fn it_applies_global_rules() {
+        let card = card("Pikachu", 51, Rarity::Rare, Class::Foil);
+
+        assert_eq!(
+            classify(&card, "Temporal Forces MASTER  SET incl. Pikachu").rejected_by,
+            Some("global_sealed".into())
+        );
+        assert!(classify(&card, "Pikachu 051/162 Holo")
+            .rejected_by
+            .is_none());
+    }
+
+    #[test]
+    fn it_applies_class_rules() {
+        let regular = card("Pikachu", 51, Rarity::Rare, Class::Regular);
+        let reverse = card("Pikachu", 51, Rarity::Rare, Class::ReverseHolo);
+
+        assert_eq!(
+            classify(&regular, "Pikachu 051/162 Reverse Holo").rejected_by,
+            Some("regular_reverse".into())
+        );
+        assert!(classify(&regular, "Pikachu 051/162 Reverseholo")
+            .rejected_by
+            .is_none());
+        assert!(classify(&reverse, "Pikachu 051/162 Reverse Holo")
+            .rejected_by
+            .is_none());
+        assert_eq!(
+            classify(&reverse, "Pikachu 051/162 Temporal Forces").rejected_by,
+            Some("reverse_holo_whitelist".into())
+        );
+    }
+
+    #[test]
+    fn it_flags_without_rejecting() {
+        let common = card("Pikachu", 51, Rarity::Common, Class::Foil);
+        let rare = card("Pikachu", 51, Rarity::Rare, Class::Foil);
+
+        let classification = classify(&common, "Japanese Pikachu 051/162");
+        assert!(classification.rejected_by.is_none());
+        assert_eq!(
+            classification.flagged_by,
+            vec!["common_japanese".to_string()]
+        );
+        assert!(classify(&rare, "Japanese Pikachu 051/162")
+            .flagged_by
+            .is_empty());
+    }
+
+    #[test]
+    fn it_applies_card_rules_with_regexes() {
+        let mut card = card("Pikachu", 51, Rarity::Rare, Class::Foil);
+
+        assert_eq!(
+            classify(&card, "Pikachu 051/162 Stamped").rejected_by,
+            Some("tef_051_promo".into())
+        );
+
+        card.number = 52;
+        assert!(classify(&card, "Pikachu 051/162 Stamped")
+            .rejected_by
+            .is_none());
+    }
+
+    #[test]
+    fn it_rejects_invalid_rules() {
+        assert!(r#"
+            [[rule]]
+            name = "broken"
+            kind = "exclude"
+            regex = true
+            patterns = ["("]
+        "#
+        .parse::<TitleRules>()
+        .is_err());
+        assert!(r#"
+            [[rule]]
+            name = "typo"
+            kind = "exclude"
+            patterns = ["x"]
+            clas = "Regular"
+        "#
+        .parse::<TitleRules>()
+        .is_err());
+    }
+
+    #[test]
+    fn it_reads_the_bundled_rules() {
+        let Ok(rules) = TitleRules::load("title_rules.toml") else {
+            panic!("Failed to read title rules");
+        };
+
+        assert!(!rules.rules.is_empty());
+    }
+}
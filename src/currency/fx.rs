@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, sqlite::Sqlite};
+
+use super::{Currency, MoneyError, CURRENCIES};
+
+/// Decimal places kept for exchange rates.
+const RATE_DECIMALS: u32 = 9;
+const RATE_SCALE: u64 = 10u64.pow(RATE_DECIMALS);
+
+/// Units of the quote currency bought by one unit of the base currency.
+///
+/// Kept as an integer number of billionths so that conversions don't go through floats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rate(u64);
+
+impl std::str::FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (major, minor) = s.split_once('.').unwrap_or((s, ""));
+
+        if minor.len() > RATE_DECIMALS as usize || !minor.chars().all(|x| x.is_ascii_digit()) {
+            return Err(format!(
+                "Invalid rate {s}, expected at most {RATE_DECIMALS} decimal places"
+            ));
+        }
+
+        let rate = major
+            .parse::<u64>()
+            .ok()
+            .and_then(|x| x.checked_mul(RATE_SCALE))
+            .and_then(|x| {
+                let minor = format!("{minor:0<0$}", RATE_DECIMALS as usize);
+                x.checked_add(minor.parse().ok()?)
+            })
+            .ok_or(format!("Invalid rate {s}"))?;
+
+        match rate {
+            0 => Err(format!("Invalid rate {s}, must be more than zero")),
+            x => Ok(Self(x)),
+        }
+    }
+}
+
+impl TryFrom<String> for Rate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Rate> for String {
+    fn from(value: Rate) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minor = format!("{:0>1$}", self.0 % RATE_SCALE, RATE_DECIMALS as usize);
+        let minor = minor.trim_end_matches('0');
+
+        match minor {
+            "" => write!(f, "{}", self.0 / RATE_SCALE),
+            _ => write!(f, "{}.{minor}", self.0 / RATE_SCALE),
+        }
+    }
+}
+
+/// The rate between two currencies on a day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct FxRate {
+    pub date: NaiveDate,
+    pub base: String,
+    pub quote: String,
+    #[sqlx(try_from = "String")]
+    pub rate: Rate,
+}
+
+impl FxRate {
+    /// Checks both currencies are supported.
+    pub fn validate(&self) -> Result<(), String> {
+        for code in [&self.base, &self.quote] {
+            super::from_code(code).ok_or(format!("Unknown currency {code}"))?;
+        }
+
+        match self.base.eq_ignore_ascii_case(&self.quote) {
+            true => Err(format!("Rate from {} to itself", self.base)),
+            false => Ok(()),
+        }
+    }
+}
+
+/// A conversion factor as an exact fraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Ratio {
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+impl Ratio {
+    const ONE: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    fn of(rate: Rate) -> Self {
+        Self {
+            numerator: rate.0.into(),
+            denominator: RATE_SCALE.into(),
+        }
+    }
+
+    fn inverse(self) -> Self {
+        Self {
+            numerator: self.denominator,
+            denominator: self.numerator,
+        }
+    }
+
+    fn then(self, other: Self) -> Result<Self, MoneyError> {
+        Ok(Self {
+            numerator: self
+                .numerator
+                .checked_mul(other.numerator)
+                .ok_or(MoneyError::Overflow)?,
+            denominator: self
+                .denominator
+                .checked_mul(other.denominator)
+                .ok_or(MoneyError::Overflow)?,
+        })
+    }
+}
+
+/// One rate per currency pair, used to convert `Money`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExchangeRates {
+    rates: HashMap<(&'static str, &'static str), Rate>,
+}
+
+impl ExchangeRates {
+    /// Keeps the most recent rate of each pair, ignoring unsupported currencies.
+    pub fn new(rates: impl IntoIterator<Item = FxRate>) -> Self {
+        let mut latest = HashMap::<_, (NaiveDate, Rate)>::new();
+
+        for x in rates {
+            let (Some(base), Some(quote)) = (super::from_code(&x.base), super::from_code(&x.quote))
+            else {
+                continue;
+            };
+
+            let entry = latest
+                .entry((base.code, quote.code))
+                .or_insert((x.date, x.rate));
+            if x.date >= entry.0 {
+                *entry = (x.date, x.rate);
+            }
+        }
+
+        Self {
+            rates: latest.into_iter().map(|(k, (_, rate))| (k, rate)).collect(),
+        }
+    }
+
+    /// Loads the most recent rate of every pair.
+    pub async fn latest(pool: &sqlx::Pool<Sqlite>) -> Result<Self, String> {
+        list_latest(pool).await.map(Self::new)
+    }
+
+    fn direct(&self, from: &Currency, to: &Currency) -> Option<Ratio> {
+        self.rates
+            .get(&(from.code, to.code))
+            .map(|x| Ratio::of(*x))
+            .or_else(|| {
+                self.rates
+                    .get(&(to.code, from.code))
+                    .map(|x| Ratio::of(*x).inverse())
+            })
+    }
+
+    /// Finds the factor converting an amount in major units of one currency to another, going
+    /// through a third currency if there's no rate between them.
+    pub(super) fn ratio(
+        &self,
+        from: &'static Currency,
+        to: &'static Currency,
+    ) -> Result<Ratio, MoneyError> {
+        if from == to {
+            return Ok(Ratio::ONE);
+        }
+        if let Some(ratio) = self.direct(from, to) {
+            return Ok(ratio);
+        }
+
+        let (first, second) = CURRENCIES
+            .into_iter()
+            .find_map(|via| Some((self.direct(from, via)?, self.direct(via, to)?)))
+            .ok_or(MoneyError::NoExchangeRate {
+                from: from.code,
+                to: to.code,
+            })?;
+
+        first.then(second)
+    }
+}
+
+impl std::str::FromStr for ExchangeRates {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_csv(s).map(Self::new)
+    }
+}
+
+/// Parses rates from a CSV with a `date,base,quote,rate` header.
+pub fn parse_csv(s: &str) -> Result<Vec<FxRate>, String> {
+    let mut lines = s
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    match lines.next() {
+        Some((_, header))
+            if header
+                .split(',')
+                .map(str::trim)
+                .eq(["date", "base", "quote", "rate"]) => {}
+        _ => return Err("Expected a date,base,quote,rate header".into()),
+    }
+
+    lines
+        .map(|(i, line)| {
+            let error = |e: String| format!("Invalid rate on line {}: {e}", i + 1);
+            let [date, base, quote, rate] = line
+                .split(',')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|_| error("expected 4 columns".into()))?;
+
+            let rate = FxRate {
+                date: date.parse().map_err(|e| error(format!("{e}")))?,
+                base: base.to_uppercase(),
+                quote: quote.to_uppercase(),
+                rate: rate.parse().map_err(error)?,
+            };
+            rate.validate().map_err(error)?;
+
+            Ok(rate)
+        })
+        .collect()
+}
+
+/// Stores rates, replacing any already stored for the same day and pair.
+pub async fn store(pool: &sqlx::Pool<Sqlite>, rates: &[FxRate]) -> Result<(), String> {
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error creating transaction: {e}"))?;
+
+    for rate in rates {
+        sqlx::query("INSERT OR REPLACE INTO fx_rates (date, base, quote, rate) VALUES (?,?,?,?)")
+            .bind(rate.date)
+            .bind(rate.base.to_uppercase())
+            .bind(rate.quote.to_uppercase())
+            .bind(rate.rate.to_string())
+            .execute(&mut *txn)
+            .await
+            .map_err(|e| format!("Failed to store exchange rate: {e}"))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit exchange rates: {e}"))
+}
+
+/// Stores the rates in a CSV file, returning how many there were.
+pub async fn load_csv(
+    pool: &sqlx::Pool<Sqlite>,
+    path: impl AsRef<std::path::Path>,
+) -> Result<usize, String> {
+    let path = path.as_ref();
+    let rates = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read exchange rates {}: {e}", path.display()))
+        .and_then(|x| parse_csv(&x))?;
+
+    store(pool, &rates).await?;
+
+    Ok(rates.len())
+}
+
+/// The most recent rate of every pair.
+pub async fn list_latest(pool: &sqlx::Pool<Sqlite>) -> Result<Vec<FxRate>, String> {
+    sqlx::query_as::<_, FxRate>(
+        "
+        SELECT date, base, quote, rate
+        FROM fx_rates
+        WHERE date = (
+            SELECT MAX(date)
+            FROM fx_rates AS newer
+            WHERE newer.base = fx_rates.base AND newer.quote = fx_rates.quote
+        )
+        ORDER BY base, quote
+        ",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to get exchange rates: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{GBP, USD};
+
+    #[test]
+    fn it_parses_rates_without_floats() {
+        assert_eq!("1.28".parse::<Rate>(), Ok(Rate(1_280_000_000)));
+        assert_eq!("200".parse::<Rate>(), Ok(Rate(200 * RATE_SCALE)));
+        assert_eq!("0.000000001".parse::<Rate>(), Ok(Rate(1)));
+        assert_eq!(Rate(1_280_000_000).to_string(), "1.28");
+        assert_eq!(Rate(200 * RATE_SCALE).to_string(), "200");
+        assert!("0".parse::<Rate>().is_err());
+        assert!("1.0000000001".parse::<Rate>().is_err());
+        assert!("-1".parse::<Rate>().is_err());
+        assert!("1.2e3".parse::<Rate>().is_err());
+    }
+
+    #[test]
+    fn it_parses_a_csv_and_keeps_the_latest_rates() {
+        let csv = "
+            date,base,quote,rate
+            2024-06-01,GBP,USD,1.25
+            2024-06-03,gbp,usd,1.28
+            2024-06-02,GBP,USD,1.27
+        ";
+
+        let Ok(rates) = csv.parse::<ExchangeRates>() else {
+            panic!("Failed to parse rates");
+        };
+        assert_eq!(rates.ratio(GBP, USD), Ok(Ratio::of(Rate(1_280_000_000))));
+    }
+
+    #[test]
+    fn it_rejects_invalid_csvs() {
+        assert!(parse_csv("2024-06-01,GBP,USD,1.25").is_err());
+        assert!(parse_csv("date,base,quote,rate\n2024-06-01,GBP,XYZ,1.25").is_err());
+        assert!(parse_csv("date,base,quote,rate\n2024-06-01,GBP,GBP,1").is_err());
+        assert!(parse_csv("date,base,quote,rate\n2024-06-01,GBP,USD").is_err());
+    }
+}
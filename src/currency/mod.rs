@@ -1,5 +1,19 @@
-#[derive(Debug)]
+use fx::ExchangeRates;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    sqlite::{SqliteArgumentValue, SqliteRow, SqliteTypeInfo, SqliteValueRef},
+    Row, Sqlite,
+};
+
+pub mod fx;
+pub mod parse;
+
+#[derive(Debug, PartialEq, Eq)]
 pub struct Currency {
+    /// ISO 4217 code, stored against every price.
+    code: &'static str,
     symbol: char,
     exponent_seperator: char,
     digit_seperator: char,
@@ -7,56 +21,330 @@ pub struct Currency {
 }
 
 pub const GBP: &Currency = &Currency {
+    code: "GBP",
     symbol: '£',
     exponent_seperator: '.',
     digit_seperator: ',',
     exponent: 2,
 };
 
-#[derive(Debug)]
-pub struct Money<'a> {
-    currency: &'a Currency,
-    total: u64,
+pub const USD: &Currency = &Currency {
+    code: "USD",
+    symbol: '$',
+    exponent_seperator: '.',
+    digit_seperator: ',',
+    exponent: 2,
+};
+
+pub const EUR: &Currency = &Currency {
+    code: "EUR",
+    symbol: '€',
+    exponent_seperator: ',',
+    digit_seperator: '.',
+    exponent: 2,
+};
+
+pub const JPY: &Currency = &Currency {
+    code: "JPY",
+    symbol: '¥',
+    exponent_seperator: '.',
+    digit_seperator: ',',
+    exponent: 0,
+};
+
+/// Every supported currency.
+pub const CURRENCIES: [&Currency; 4] = [GBP, USD, EUR, JPY];
+
+/// Looks up a supported currency by its ISO 4217 code.
+pub fn from_code(code: &str) -> Option<&'static Currency> {
+    CURRENCIES
+        .into_iter()
+        .find(|x| x.code.eq_ignore_ascii_case(code))
 }
 
-impl<'a> Money<'a> {
-    pub fn from_str(s: &str, currency: &'a Currency) -> Result<Self, String> {
-        let s = if s.starts_with(currency.symbol) {
-            s.chars().skip(1).collect::<String>()
-        } else {
-            s.to_string()
-        };
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Number of minor unit digits, i.e. 2 for pence and 0 for yen.
+    pub fn exponent(&self) -> u8 {
+        self.exponent
+    }
+}
 
-        let mut xs = s.split(currency.exponent_seperator).collect::<Vec<_>>();
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+impl std::str::FromStr for &'static Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_code(s.trim()).ok_or(format!("Unknown currency {s}"))
+    }
+}
+
+/// Why arithmetic on `Money` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    CurrencyMismatch {
+        left: &'static str,
+        right: &'static str,
+    },
+    Overflow,
+    /// The result would be negative, which amounts can't be.
+    Negative,
+    DivideByZero,
+    /// Nothing was summed, which leaves the total without a currency.
+    Empty,
+    /// There's no rate between the currencies, directly or through a third currency.
+    NoExchangeRate {
+        from: &'static str,
+        to: &'static str,
+    },
+}
+
+impl std::fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CurrencyMismatch { left, right } => {
+                write!(f, "Can't combine {left} with {right}")
+            }
+            Self::Overflow => write!(f, "Amount is too large"),
+            Self::Negative => write!(f, "Amount would be negative"),
+            Self::DivideByZero => write!(f, "Divided by zero"),
+            Self::Empty => write!(f, "Summed no amounts"),
+            Self::NoExchangeRate { from, to } => write!(f, "No exchange rate from {from} to {to}"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// How a result between two minor units is rounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Towards zero.
+    Down,
+    /// Away from zero.
+    Up,
+    /// To the nearest minor unit, with halves rounded up.
+    HalfUp,
+    /// To the nearest minor unit, with halves rounded to the even neighbour.
+    HalfEven,
+}
+
+impl Rounding {
+    fn divide(self, numerator: u128, denominator: u128) -> u128 {
+        let (quotient, remainder) = (numerator / denominator, numerator % denominator);
 
-        if xs.len() == 1 {
-            xs.push("00");
-        } else if xs.len() > 2 {
-            return Err("Invalid number of parts".into());
+        let round_up = match self {
+            Self::Down => false,
+            Self::Up => remainder > 0,
+            Self::HalfUp => remainder * 2 >= denominator,
+            Self::HalfEven => match (remainder * 2).cmp(&denominator) {
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => quotient % 2 == 1,
+                std::cmp::Ordering::Greater => true,
+            },
         };
 
-        let major = xs[0].split(currency.digit_seperator).collect::<String>();
-        let minor = format!("{:0<1$}", xs[1], currency.exponent.into());
-        let total = format!("{major}{minor}");
+        quotient + u128::from(round_up)
+    }
+}
+
+/// An amount in minor units of a currency.
+///
+/// Arithmetic is checked and fails rather than mixing currencies, overflowing or going negative.
+/// Amounts in different currencies are ordered by currency code first, so use
+/// [`Money::checked_cmp`] where they must be comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Money {
+    currency: &'static Currency,
+    /// Minor units.
+    #[serde(rename = "amount")]
+    total: u64,
+}
+
+impl Money {
+    /// Money from an amount in minor units.
+    pub fn new(total: u64, currency: &'static Currency) -> Self {
+        Self { currency, total }
+    }
+
+    pub fn zero(currency: &'static Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Parses a single price in `currency`, see [`parse::parse`].
+    pub fn from_str(s: &str, currency: &'static Currency) -> Result<Self, String> {
+        match parse::parse(s, currency).map_err(|e| e.to_string())? {
+            parse::Price::Single(money) if money.currency == currency => Ok(money),
+            parse::Price::Single(money) => Err(format!(
+                "Expected a price in {currency}, found {}",
+                money.currency
+            )),
+            price => Err(format!("Expected a single price, found {price}")),
+        }
+    }
+
+    pub fn currency(&self) -> &'static Currency {
+        self.currency
+    }
+
+    /// The amount in minor units.
+    pub fn amount(&self) -> u64 {
+        self.total
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        match self.currency == other.currency {
+            true => Ok(()),
+            false => Err(MoneyError::CurrencyMismatch {
+                left: self.currency.code,
+                right: other.currency.code,
+            }),
+        }
+    }
+
+    fn with_total(&self, total: u128) -> Result<Money, MoneyError> {
+        Ok(Money::new(
+            u64::try_from(total).map_err(|_| MoneyError::Overflow)?,
+            self.currency,
+        ))
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        self.with_total(u128::from(self.total) + u128::from(other.total))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        self.total
+            .checked_sub(other.total)
+            .map(|x| Money::new(x, self.currency))
+            .ok_or(MoneyError::Negative)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Result<Money, MoneyError> {
+        self.with_total(u128::from(self.total) * u128::from(factor))
+    }
+
+    /// Divides into `divisor` equal parts, rounding each part to a minor unit.
+    pub fn div_rounded(self, divisor: u64, rounding: Rounding) -> Result<Money, MoneyError> {
+        self.mul_ratio(1, divisor, rounding)
+    }
+
+    /// Multiplies by `numerator / denominator` without rounding until the end.
+    pub fn mul_ratio(
+        self,
+        numerator: u64,
+        denominator: u64,
+        rounding: Rounding,
+    ) -> Result<Money, MoneyError> {
+        if denominator == 0 {
+            return Err(MoneyError::DivideByZero);
+        }
+
+        self.with_total(rounding.divide(
+            u128::from(self.total) * u128::from(numerator),
+            denominator.into(),
+        ))
+    }
+
+    /// Compares amounts, failing if they're in different currencies.
+    pub fn checked_cmp(&self, other: &Money) -> Result<std::cmp::Ordering, MoneyError> {
+        self.same_currency(other)?;
+        Ok(self.total.cmp(&other.total))
+    }
+
+    /// Converts to another currency at the latest rate, rounding half up to the nearest minor
+    /// unit.
+    pub fn convert(
+        &self,
+        to: &'static Currency,
+        rates: &ExchangeRates,
+    ) -> Result<Money, MoneyError> {
+        let ratio = rates.ratio(self.currency, to)?;
+
+        // INFO: Scaling by both exponents before dividing keeps the conversion exact until the
+        // final rounding
+        let numerator = u128::from(self.total)
+            .checked_mul(ratio.numerator)
+            .and_then(|x| x.checked_mul(10u128.pow(to.exponent.into())))
+            .ok_or(MoneyError::Overflow)?;
+        let denominator = ratio
+            .denominator
+            .checked_mul(10u128.pow(self.currency.exponent.into()))
+            .ok_or(MoneyError::Overflow)?;
+
+        Money::zero(to).with_total(Rounding::HalfUp.divide(numerator, denominator))
+    }
+}
+
+impl std::ops::Add for Money {
+    type Output = Result<Money, MoneyError>;
+
+    fn add(self, rhs: Money) -> Self::Output {
+        self.checked_add(rhs)
+    }
+}
+
+impl std::ops::Sub for Money {
+    type Output = Result<Money, MoneyError>;
+
+    fn sub(self, rhs: Money) -> Self::Output {
+        self.checked_sub(rhs)
+    }
+}
+
+impl std::ops::Mul<u64> for Money {
+    type Output = Result<Money, MoneyError>;
+
+    fn mul(self, rhs: u64) -> Self::Output {
+        self.checked_mul(rhs)
+    }
+}
+
+impl std::iter::Sum<Money> for Result<Money, MoneyError> {
+    fn sum<I: Iterator<Item = Money>>(mut iter: I) -> Self {
+        let first = iter.next().ok_or(MoneyError::Empty)?;
+        iter.try_fold(first, Money::checked_add)
+    }
+}
 
-        Ok(Money {
-            currency,
-            total: total.parse().map_err(|_| "Failed to parse total")?,
-        })
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-impl std::convert::From<&Money<'_>> for u64 {
-    fn from(value: &Money<'_>) -> Self {
+impl Ord for Money {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.currency.code, self.total).cmp(&(other.currency.code, other.total))
+    }
+}
+
+impl std::convert::From<&Money> for u64 {
+    fn from(value: &Money) -> Self {
         value.total
     }
 }
 
-impl std::fmt::Display for Money<'_> {
+impl std::fmt::Display for Money {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let scale = 10u64.pow(self.currency.exponent.into());
         let major = self.total / scale;
         let minor = self.total % scale;
+
+        if self.currency.exponent == 0 {
+            return write!(f, "{}{}", self.currency.symbol, major);
+        }
+
         write!(
             f,
             "{}{}{}{:0>4$}",
@@ -69,6 +357,74 @@ impl std::fmt::Display for Money<'_> {
     }
 }
 
+/// Serialised as its code.
+impl Serialize for Currency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code)
+    }
+}
+
+impl<'de> Deserialize<'de> for &'static Currency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl sqlx::Type<Sqlite> for &'static Currency {
+    fn type_info() -> SqliteTypeInfo {
+        <&str as sqlx::Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for &'static Currency {
+    fn encode_by_ref(
+        &self,
+        args: &mut Vec<SqliteArgumentValue<'q>>,
+    ) -> Result<IsNull, BoxDynError> {
+        <&str as sqlx::Encode<Sqlite>>::encode_by_ref(&self.code, args)
+    }
+}
+
+impl sqlx::Decode<'_, Sqlite> for &'static Currency {
+    fn decode(value: SqliteValueRef<'_>) -> Result<Self, BoxDynError> {
+        let code = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        from_code(code).ok_or(format!("Unknown currency {code}").into())
+    }
+}
+
+/// Stored as its amount in minor units, with the currency in a column of its own.
+impl sqlx::Type<Sqlite> for Money {
+    fn type_info() -> SqliteTypeInfo {
+        <i64 as sqlx::Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for Money {
+    fn encode_by_ref(
+        &self,
+        args: &mut Vec<SqliteArgumentValue<'q>>,
+    ) -> Result<IsNull, BoxDynError> {
+        <i64 as sqlx::Encode<Sqlite>>::encode_by_ref(&i64::try_from(self.total)?, args)
+    }
+}
+
+/// Reads the `price` and `currency` columns, which structs can `#[sqlx(flatten)]` into a field.
+impl sqlx::FromRow<'_, SqliteRow> for Money {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        let total = row.try_get::<i64, _>("price")?;
+
+        Ok(Money::new(
+            u64::try_from(total).map_err(|e| sqlx::Error::ColumnDecode {
+                index: "price".into(),
+                source: e.into(),
+            })?,
+            row.try_get("currency")?,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -76,14 +432,15 @@ mod tests {
 
     #[test]
     fn it_works_with_a_left_aligned_exponent() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("£1,000,000.50", &gbp) else {
+        let Ok(money) = Money::from_str("£1,000,000.50", gbp) else {
             panic!("Failed to parse money");
         };
 
@@ -92,14 +449,15 @@ mod tests {
 
     #[test]
     fn it_works_with_a_right_aligned_exponent() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("£1,000,000.05", &gbp) else {
+        let Ok(money) = Money::from_str("£1,000,000.05", gbp) else {
             panic!("Failed to parse money");
         };
 
@@ -108,14 +466,15 @@ mod tests {
 
     #[test]
     fn it_works_with_no_exponent() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("£1,000,000", &gbp) else {
+        let Ok(money) = Money::from_str("£1,000,000", gbp) else {
             panic!("Failed to parse money");
         };
 
@@ -124,14 +483,15 @@ mod tests {
 
     #[test]
     fn it_works_with_no_formatting() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("1000000", &gbp) else {
+        let Ok(money) = Money::from_str("1000000", gbp) else {
             panic!("Failed to parse money");
         };
 
@@ -140,14 +500,15 @@ mod tests {
 
     #[test]
     fn it_converts_to_a_u64() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("1000000", &gbp) else {
+        let Ok(money) = Money::from_str("1000000", gbp) else {
             panic!("Failed to parse money");
         };
 
@@ -156,17 +517,195 @@ mod tests {
 
     #[test]
     fn it_implements_display() {
-        let gbp = Currency {
+        let gbp: &Currency = &Currency {
+            code: "GBP",
             symbol: '£',
             exponent_seperator: '.',
             digit_seperator: ',',
             exponent: 2,
         };
 
-        let Ok(money) = Money::from_str("£1,234.05", &gbp) else {
+        let Ok(money) = Money::from_str("£1,234.05", gbp) else {
             panic!("Failed to parse money");
         };
 
         assert!(money.to_string() == "£1234.05");
     }
+
+    fn rates() -> ExchangeRates {
+        let Ok(rates) = "date,base,quote,rate\n2024-06-03,GBP,USD,1.28\n2024-06-03,GBP,JPY,200.5\n"
+            .parse::<ExchangeRates>()
+        else {
+            panic!("Failed to parse rates");
+        };
+
+        rates
+    }
+
+    #[test]
+    fn it_looks_up_currencies_by_code() {
+        assert_eq!(from_code("usd"), Some(USD));
+        assert_eq!("JPY".parse::<&Currency>(), Ok(JPY));
+        assert!(from_code("XYZ").is_none());
+    }
+
+    #[test]
+    fn it_handles_currencies_without_minor_units() {
+        let Ok(money) = Money::from_str("¥1,500", JPY) else {
+            panic!("Failed to parse money");
+        };
+
+        assert_eq!(u64::from(&money), 1500);
+        assert_eq!(money.to_string(), "¥1500");
+    }
+
+    #[test]
+    fn it_converts_between_currencies() {
+        let rates = rates();
+
+        let Ok(usd) = Money::new(1000, GBP).convert(USD, &rates) else {
+            panic!("Failed to convert to USD");
+        };
+        assert_eq!(usd, Money::new(1280, USD));
+
+        let Ok(gbp) = Money::new(1000, USD).convert(GBP, &rates) else {
+            panic!("Failed to convert from USD");
+        };
+        assert_eq!(gbp, Money::new(781, GBP));
+
+        let Ok(jpy) = Money::new(1250, GBP).convert(JPY, &rates) else {
+            panic!("Failed to convert to JPY");
+        };
+        assert_eq!(jpy, Money::new(2506, JPY));
+
+        let Ok(cross) = Money::new(1280, USD).convert(JPY, &rates) else {
+            panic!("Failed to convert through GBP");
+        };
+        assert_eq!(cross, Money::new(2005, JPY));
+
+        assert_eq!(
+            Money::new(1, EUR).convert(EUR, &rates),
+            Ok(Money::new(1, EUR))
+        );
+        assert_eq!(
+            Money::new(1, EUR).convert(GBP, &rates),
+            Err(MoneyError::NoExchangeRate {
+                from: "EUR",
+                to: "GBP"
+            })
+        );
+    }
+
+    #[test]
+    fn it_fails_to_convert_amounts_that_overflow() {
+        let Ok(rates) = "date,base,quote,rate\n2024-06-03,GBP,USD,18446744073.709551615\n"
+            .parse::<ExchangeRates>()
+        else {
+            panic!("Failed to parse rates");
+        };
+
+        assert_eq!(
+            Money::new(u64::MAX, GBP).convert(USD, &rates),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(
+            Money::new(10_000_000_000, GBP).convert(USD, &rates),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn it_adds_and_subtracts_in_one_currency() {
+        assert_eq!(
+            Money::new(150, GBP) + Money::new(250, GBP),
+            Ok(Money::new(400, GBP))
+        );
+        assert_eq!(
+            Money::new(250, GBP) - Money::new(150, GBP),
+            Ok(Money::new(100, GBP))
+        );
+        assert_eq!(
+            Money::new(150, GBP) - Money::new(250, GBP),
+            Err(MoneyError::Negative)
+        );
+        assert_eq!(
+            Money::new(150, GBP) + Money::new(250, USD),
+            Err(MoneyError::CurrencyMismatch {
+                left: "GBP",
+                right: "USD"
+            })
+        );
+        assert_eq!(
+            Money::new(u64::MAX, GBP) + Money::new(1, GBP),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(Money::new(250, GBP) * 3, Ok(Money::new(750, GBP)));
+        assert_eq!(Money::new(u64::MAX, GBP) * 2, Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn it_sums_amounts() {
+        let total = [100, 200, 300]
+            .into_iter()
+            .map(|x| Money::new(x, GBP))
+            .sum::<Result<Money, MoneyError>>();
+        assert_eq!(total, Ok(Money::new(600, GBP)));
+
+        let empty = std::iter::empty().sum::<Result<Money, MoneyError>>();
+        assert_eq!(empty, Err(MoneyError::Empty));
+
+        let mixed = [Money::new(100, GBP), Money::new(100, JPY)]
+            .into_iter()
+            .sum::<Result<Money, MoneyError>>();
+        assert!(mixed.is_err());
+    }
+
+    #[test]
+    fn it_divides_with_a_rounding_mode() {
+        let divide = |total, divisor, rounding| {
+            Money::new(total, GBP)
+                .div_rounded(divisor, rounding)
+                .map(|x| x.amount())
+        };
+
+        assert_eq!(divide(10, 4, Rounding::Down), Ok(2));
+        assert_eq!(divide(10, 4, Rounding::Up), Ok(3));
+        assert_eq!(divide(10, 4, Rounding::HalfUp), Ok(3));
+        assert_eq!(divide(10, 4, Rounding::HalfEven), Ok(2));
+        assert_eq!(divide(14, 4, Rounding::HalfEven), Ok(4));
+        assert_eq!(divide(11, 4, Rounding::HalfEven), Ok(3));
+        assert_eq!(divide(8, 4, Rounding::Up), Ok(2));
+        assert_eq!(divide(10, 0, Rounding::Down), Err(MoneyError::DivideByZero));
+        assert_eq!(
+            Money::new(100, GBP).mul_ratio(2, 3, Rounding::HalfUp),
+            Ok(Money::new(67, GBP))
+        );
+    }
+
+    #[test]
+    fn it_orders_amounts() {
+        assert!(Money::new(100, GBP) < Money::new(200, GBP));
+        assert_eq!(
+            Money::new(300, GBP).checked_cmp(&Money::new(200, GBP)),
+            Ok(std::cmp::Ordering::Greater)
+        );
+        assert!(Money::new(100, GBP)
+            .checked_cmp(&Money::new(100, USD))
+            .is_err());
+    }
+
+    #[test]
+    fn it_serialises_amounts_with_their_currency() {
+        let Ok(json) = serde_json::to_string(&Money::new(499, EUR)) else {
+            panic!("Failed to serialise money");
+        };
+        assert_eq!(json, r#"{"currency":"EUR","amount":499}"#);
+
+        let Ok(money) = serde_json::from_str::<Money>(&json) else {
+            panic!("Failed to deserialise money");
+        };
+        assert_eq!(money, Money::new(499, EUR));
+
+        assert!(serde_json::from_str::<Money>(r#"{"currency":"XYZ","amount":1}"#).is_err());
+    }
 }
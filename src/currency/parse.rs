@@ -0,0 +1,473 @@
+use super::{Currency, Money, MoneyError, USD};
+
+/// Text marking an amount's currency, checked in order so that longer markers win.
+const MARKERS: [(&str, &Currency); 10] = [
+    ("US $", USD),
+    ("US$", USD),
+    ("GBP", super::GBP),
+    ("USD", USD),
+    ("EUR", super::EUR),
+    ("JPY", super::JPY),
+    ("£", super::GBP),
+    ("$", USD),
+    ("€", super::EUR),
+    ("¥", super::JPY),
+];
+
+/// Words that follow a postage amount, longest first.
+const POSTAGE_WORDS: [&str; 6] = [
+    "postage and packaging",
+    "postage & packaging",
+    "postage",
+    "delivery",
+    "shipping",
+    "p&p",
+];
+
+/// A price as shown by a marketplace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Price {
+    Single(Money),
+    /// A listing with variations, i.e. "£5.00 to £12.00".
+    Range {
+        low: Money,
+        high: Money,
+    },
+    WithPostage {
+        price: Money,
+        postage: Money,
+    },
+}
+
+impl std::fmt::Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single(price) => write!(f, "{price}"),
+            Self::Range { low, high } => write!(f, "{low} to {high}"),
+            Self::WithPostage { price, postage } if postage.amount() == 0 => {
+                write!(f, "{price} + Free postage")
+            }
+            Self::WithPostage { price, postage } => write!(f, "{price} + {postage} postage"),
+        }
+    }
+}
+
+/// Why a price couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PriceError {
+    Empty,
+    /// Text around the amount that isn't a supported currency, i.e. "AU $".
+    UnknownCurrency(String),
+    InvalidAmount(String),
+    /// More minor digits than the currency has, i.e. "£1.234".
+    TooManyMinorDigits {
+        amount: String,
+        currency: &'static str,
+    },
+    Overflow(String),
+    /// The two sides of a range or a price and its postage are in different currencies.
+    CurrencyMismatch {
+        left: &'static str,
+        right: &'static str,
+    },
+    /// A range that ends lower than it starts.
+    ReversedRange(String),
+}
+
+impl std::fmt::Display for PriceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Price is empty"),
+            Self::UnknownCurrency(x) => write!(f, "Unknown currency {x}"),
+            Self::InvalidAmount(x) => write!(f, "Invalid amount {x}"),
+            Self::TooManyMinorDigits { amount, currency } => {
+                write!(f, "Too many minor digits in {amount} for {currency}")
+            }
+            Self::Overflow(x) => write!(f, "Amount {x} is too large"),
+            Self::CurrencyMismatch { left, right } => {
+                write!(f, "Can't combine {left} with {right}")
+            }
+            Self::ReversedRange(x) => write!(f, "Range {x} ends lower than it starts"),
+        }
+    }
+}
+
+impl std::error::Error for PriceError {}
+
+impl From<MoneyError> for PriceError {
+    fn from(value: MoneyError) -> Self {
+        match value {
+            MoneyError::CurrencyMismatch { left, right } => Self::CurrencyMismatch { left, right },
+            e => Self::InvalidAmount(e.to_string()),
+        }
+    }
+}
+
+/// Parses a single price, a range or a price with postage, i.e. "£12.99", "£5.00 to £12.00",
+/// "1.234,56 €", "US $12.99" or "£12.99 + £1.50 postage".
+///
+/// Amounts without a currency are in the other side's currency, or `default` if neither has one.
+/// Both "1,234.56" and "1.234,56" are understood whatever the currency, and a lone separator is
+/// read as the currency's decimal separator unless it's followed by exactly three digits.
+pub fn parse(s: &str, default: &'static Currency) -> Result<Price, PriceError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(PriceError::Empty);
+    }
+
+    if let Some((price, postage)) = s.split_once('+') {
+        let price = parse_amount(price, default)?;
+        let postage = parse_postage(postage, price.currency())?;
+        price.checked_cmp(&postage)?;
+
+        return Ok(Price::WithPostage { price, postage });
+    }
+
+    if let Some((low, high)) = split_range(s) {
+        let (low_currency, low_digits) = split_currency(low)?;
+        let (high_currency, high_digits) = split_currency(high)?;
+        let currency = match (low_currency, high_currency) {
+            (Some(low), Some(high)) if low != high => {
+                return Err(PriceError::CurrencyMismatch {
+                    left: low.code,
+                    right: high.code,
+                })
+            }
+            (low, high) => low.or(high).unwrap_or(default),
+        };
+
+        let low = parse_digits(low_digits, currency)?;
+        let high = parse_digits(high_digits, currency)?;
+        if low > high {
+            return Err(PriceError::ReversedRange(s.into()));
+        }
+
+        return Ok(Price::Range { low, high });
+    }
+
+    parse_amount(s, default).map(Price::Single)
+}
+
+/// Parses a postage amount, i.e. "+£1.50 postage" or "Free delivery", which is zero.
+pub fn parse_postage(s: &str, default: &'static Currency) -> Result<Money, PriceError> {
+    let s = s.trim().trim_start_matches('+').trim();
+    let s = POSTAGE_WORDS
+        .into_iter()
+        .find_map(|x| strip_suffix_ignore_case(s, x))
+        .unwrap_or(s)
+        .trim();
+
+    match s.eq_ignore_ascii_case("free") {
+        true => Ok(Money::zero(default)),
+        false => parse_amount(s, default),
+    }
+}
+
+/// Splits "£5.00 to £12.00" or "£5.00 - £12.00" into its two ends.
+fn split_range(s: &str) -> Option<(&str, &str)> {
+    s.split_once(" to ")
+        .or_else(|| s.split_once(" TO "))
+        .or_else(|| s.split_once(['-', '–']))
+}
+
+fn parse_amount(s: &str, default: &'static Currency) -> Result<Money, PriceError> {
+    let (currency, digits) = split_currency(s)?;
+    parse_digits(digits, currency.unwrap_or(default))
+}
+
+fn is_amount_char(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '.' | ',' | ' ' | '\u{a0}' | '\u{202f}')
+}
+
+/// Strips a currency marker from either end of an amount.
+fn split_currency(s: &str) -> Result<(Option<&'static Currency>, &str), PriceError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(PriceError::Empty);
+    }
+
+    for (marker, currency) in MARKERS {
+        if let Some(x) = strip_prefix_ignore_case(s, marker) {
+            return Ok((Some(currency), x.trim()));
+        }
+        if let Some(x) = strip_suffix_ignore_case(s, marker) {
+            return Ok((Some(currency), x.trim()));
+        }
+    }
+
+    let start = s.find(|c: char| c.is_ascii_digit()).unwrap_or(s.len());
+    let end = s.rfind(|c: char| c.is_ascii_digit()).map_or(0, |i| i + 1);
+    let unknown = |x: &str| (!x.chars().all(is_amount_char)).then(|| x.trim().to_string());
+
+    match unknown(&s[..start]).or_else(|| unknown(&s[end.max(start)..])) {
+        Some(x) => Err(PriceError::UnknownCurrency(x)),
+        None => Ok((None, s)),
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .filter(|x| x.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let i = s.len().checked_sub(suffix.len())?;
+    s.get(i..)
+        .filter(|x| x.eq_ignore_ascii_case(suffix))
+        .map(|_| &s[..i])
+}
+
+/// Parses an amount without its currency, i.e. "1,234.56", into minor units.
+fn parse_digits(s: &str, currency: &'static Currency) -> Result<Money, PriceError> {
+    let invalid = || PriceError::InvalidAmount(s.into());
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(PriceError::Empty);
+    }
+    if !s.chars().all(is_amount_char) {
+        return Err(invalid());
+    }
+
+    let last_dot = s.rfind('.');
+    let last_comma = s.rfind(',');
+    let decimal = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some(i), None) | (None, Some(i)) => {
+            let separator = &s[i..i + 1];
+            let lone = s.matches(separator).count() == 1;
+            let grouped = s.len() - i - 1 == 3;
+
+            (lone && (s[i..].starts_with(currency.exponent_seperator) || !grouped)).then_some(i)
+        }
+        (None, None) => None,
+    };
+
+    let (major, minor) = match decimal {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let major = ungroup(major).ok_or_else(invalid)?;
+    let minor = match minor {
+        Some(minor) if minor.is_empty() || !minor.chars().all(|c| c.is_ascii_digit()) => {
+            return Err(invalid())
+        }
+        Some(minor) if minor.len() > currency.exponent.into() => {
+            return Err(PriceError::TooManyMinorDigits {
+                amount: s.into(),
+                currency: currency.code,
+            })
+        }
+        Some(minor) => format!("{minor:0<width$}", width = currency.exponent.into()),
+        None => "0".repeat(currency.exponent.into()),
+    };
+
+    format!("{major}{minor}")
+        .parse::<u64>()
+        .map(|x| Money::new(x, currency))
+        .map_err(|_| PriceError::Overflow(s.into()))
+}
+
+/// Removes digit grouping, checking that every group after the first has three digits.
+fn ungroup(s: &str) -> Option<String> {
+    let separators = s
+        .chars()
+        .filter(|c| !c.is_ascii_digit())
+        .collect::<std::collections::HashSet<_>>();
+    if separators.len() > 1 {
+        return None;
+    }
+
+    let mut groups = s.split(|c: char| !c.is_ascii_digit());
+    let first = groups.next()?;
+    if first.is_empty() || (!separators.is_empty() && first.len() > 3) {
+        return None;
+    }
+
+    groups.try_fold(first.to_string(), |acc, x| (x.len() == 3).then(|| acc + x))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::currency::{CURRENCIES, EUR, GBP, JPY};
+
+    fn currencies() -> impl Strategy<Value = &'static Currency> {
+        proptest::sample::select(CURRENCIES.to_vec())
+    }
+
+    fn single(s: &str) -> Result<Money, PriceError> {
+        match parse(s, GBP)? {
+            Price::Single(x) => Ok(x),
+            x => panic!("Expected a single price, found {x:?}"),
+        }
+    }
+
+    /// Formats an amount with digit grouping, and the symbol after it for euros.
+    fn grouped(money: Money) -> String {
+        let currency = money.currency();
+        let scale = 10u64.pow(currency.exponent.into());
+        let major = (money.amount() / scale).to_string();
+
+        let mut digits = String::new();
+        for (i, c) in major.chars().enumerate() {
+            if i > 0 && (major.len() - i).is_multiple_of(3) {
+                digits.push(currency.digit_seperator);
+            }
+            digits.push(c);
+        }
+        if currency.exponent > 0 {
+            digits = format!(
+                "{digits}{}{:0>2$}",
+                currency.exponent_seperator,
+                money.amount() % scale,
+                currency.exponent.into()
+            );
+        }
+
+        match currency == EUR {
+            true => format!("{digits} {}", currency.symbol),
+            false => format!("{}{digits}", currency.symbol),
+        }
+    }
+
+    #[test]
+    fn it_parses_single_prices() {
+        assert_eq!(single("£12.99"), Ok(Money::new(1299, GBP)));
+        assert_eq!(single("12.5"), Ok(Money::new(1250, GBP)));
+        assert_eq!(single("£1,234"), Ok(Money::new(123_400, GBP)));
+        assert_eq!(single("1.234,56 €"), Ok(Money::new(123_456, EUR)));
+        assert_eq!(single("EUR 12,99"), Ok(Money::new(1299, EUR)));
+        assert_eq!(single("€12.99"), Ok(Money::new(1299, EUR)));
+        assert_eq!(single("US $12.99"), Ok(Money::new(1299, USD)));
+        assert_eq!(single("¥1,500"), Ok(Money::new(1500, JPY)));
+        assert_eq!(single("1 234,56 EUR"), Ok(Money::new(123_456, EUR)));
+    }
+
+    #[test]
+    fn it_parses_ranges() {
+        assert_eq!(
+            parse("£5.00 to £12.00", GBP),
+            Ok(Price::Range {
+                low: Money::new(500, GBP),
+                high: Money::new(1200, GBP)
+            })
+        );
+        assert_eq!(
+            parse("$5 - 12", GBP),
+            Ok(Price::Range {
+                low: Money::new(500, USD),
+                high: Money::new(1200, USD)
+            })
+        );
+        assert_eq!(
+            parse("£12.00 to £5.00", GBP),
+            Err(PriceError::ReversedRange("£12.00 to £5.00".into()))
+        );
+        assert_eq!(
+            parse("£5.00 to €12.00", GBP),
+            Err(PriceError::CurrencyMismatch {
+                left: "GBP",
+                right: "EUR"
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_prices_with_postage() {
+        assert_eq!(
+            parse("£12.99 +£1.50 postage", GBP),
+            Ok(Price::WithPostage {
+                price: Money::new(1299, GBP),
+                postage: Money::new(150, GBP)
+            })
+        );
+        assert_eq!(
+            parse("£12.99 + Free delivery", GBP),
+            Ok(Price::WithPostage {
+                price: Money::new(1299, GBP),
+                postage: Money::zero(GBP)
+            })
+        );
+        assert_eq!(
+            parse_postage("+£1.50 postage", GBP),
+            Ok(Money::new(150, GBP))
+        );
+        assert_eq!(parse_postage("Free Postage", EUR), Ok(Money::zero(EUR)));
+        assert!(parse("£12.99 + $1.50 postage", GBP).is_err());
+    }
+
+    #[test]
+    fn it_rejects_garbage() {
+        assert_eq!(single(""), Err(PriceError::Empty));
+        assert_eq!(
+            single("£1.234"),
+            Err(PriceError::TooManyMinorDigits {
+                amount: "1.234".into(),
+                currency: "GBP"
+            })
+        );
+        assert!(matches!(
+            single("¥12.5"),
+            Err(PriceError::TooManyMinorDigits { .. })
+        ));
+        assert_eq!(
+            single("AU $12.99"),
+            Err(PriceError::UnknownCurrency("AU $".into()))
+        );
+        assert_eq!(
+            single("£12.x"),
+            Err(PriceError::InvalidAmount("12.x".into()))
+        );
+        assert!(matches!(
+            single("£1,23.45"),
+            Err(PriceError::InvalidAmount(_))
+        ));
+        assert!(matches!(single("£12."), Err(PriceError::InvalidAmount(_))));
+        assert!(matches!(single("£.50"), Err(PriceError::InvalidAmount(_))));
+        assert!(matches!(
+            single("£999999999999999999999"),
+            Err(PriceError::Overflow(_))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn it_round_trips_single_prices(currency in currencies(), total: u64) {
+            let price = Price::Single(Money::new(total, currency));
+            prop_assert_eq!(parse(&price.to_string(), GBP), Ok(price));
+        }
+
+        #[test]
+        fn it_round_trips_grouped_prices(currency in currencies(), total in 0..u64::MAX / 10) {
+            let money = Money::new(total, currency);
+            prop_assert_eq!(parse(&grouped(money), GBP), Ok(Price::Single(money)));
+        }
+
+        #[test]
+        fn it_round_trips_ranges(currency in currencies(), a: u64, b: u64) {
+            let price = Price::Range {
+                low: Money::new(a.min(b), currency),
+                high: Money::new(a.max(b), currency),
+            };
+            prop_assert_eq!(parse(&price.to_string(), GBP), Ok(price));
+        }
+
+        #[test]
+        fn it_round_trips_postage(currency in currencies(), price: u64, postage: u64) {
+            let price = Price::WithPostage {
+                price: Money::new(price, currency),
+                postage: Money::new(postage, currency),
+            };
+            prop_assert_eq!(parse(&price.to_string(), GBP), Ok(price));
+        }
+
+        #[test]
+        fn it_never_panics(s in "\\PC*") {
+            let _ = parse(&s, GBP);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use serde::Serialize;
+use sqlx::{prelude::FromRow, sqlite::Sqlite};
+
+use crate::card_scraper::{
+    card_details::{Attack, CardDetails, EnergyCard, TrainerType},
+    retry::ScrapeError,
+    Expansion, Pokemon,
+};
+
+/// Summary of a registered expansion.
+#[derive(Debug, Serialize, FromRow)]
+pub struct RegisteredExpansion {
+    pub id: u32,
+    pub set_name: String,
+    pub expansion_name: String,
+    pub series_number: f32,
+    pub printed_total: Option<u32>,
+    pub release_date: Option<chrono::NaiveDate>,
+    pub set_code: Option<String>,
+    pub enabled: bool,
+    pub registered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Reads every `*.json` manifest in a directory, returning the raw manifest alongside the parsed
+/// expansion.
+pub fn read_directory(
+    dir: impl AsRef<std::path::Path>,
+) -> Result<Vec<(String, Expansion)>, String> {
+    let dir = dir.as_ref();
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read expansions directory {}: {e}", dir.display()))?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read expansions directory {}: {e}", dir.display()))?;
+
+    paths.retain(|x| x.extension().is_some_and(|x| x == "json"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let manifest = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read manifest {}: {e}", path.display()))?;
+            let expansion = serde_json::from_str::<Expansion>(&manifest)
+                .map_err(|e| format!("Failed to parse manifest {}: {e}", path.display()))?;
+
+            Ok((manifest, expansion))
+        })
+        .collect()
+}
+
+/// Registers every manifest found in a directory.
+pub async fn load_directory(
+    pool: &sqlx::Pool<Sqlite>,
+    dir: impl AsRef<std::path::Path>,
+) -> Result<Vec<Expansion>, String> {
+    let manifests = read_directory(dir)?;
+
+    let mut expansions = Vec::with_capacity(manifests.len());
+    for (manifest, mut expansion) in manifests {
+        expansion.id = register(pool, &manifest, &expansion).await?;
+        expansions.push(expansion);
+    }
+
+    Ok(expansions)
+}
+
+/// Stores a manifest and creates the cards it contains, returning the expansion id.
+///
+/// Re-registering an expansion replaces its manifest but keeps its enabled flag, so that a
+/// disabled set stays disabled after a redeploy.
+pub async fn register(
+    pool: &sqlx::Pool<Sqlite>,
+    manifest: &str,
+    expansion: &Expansion,
+) -> Result<u32, String> {
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error creating transaction: {e}"))?;
+
+    let (id,) = sqlx::query_as::<_, (u32,)>(
+        "
+        INSERT INTO expansions
+            (set_name, expansion_name, series_number, printed_total, release_date, set_code, manifest, registered_at)
+        VALUES
+            (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (set_name, series_number) DO UPDATE SET
+            expansion_name = excluded.expansion_name,
+            printed_total = excluded.printed_total,
+            release_date = excluded.release_date,
+            set_code = excluded.set_code,
+            manifest = excluded.manifest,
+            registered_at = COALESCE(expansions.registered_at, excluded.registered_at)
+        RETURNING id
+        ",
+    )
+    .bind(expansion.set_name.clone())
+    .bind(expansion.expansion_name.clone())
+    .bind(expansion.expansion_number)
+    .bind(expansion.expansion_total as u32)
+    .bind(expansion.release_date)
+    .bind(expansion.set_code.clone())
+    .bind(manifest)
+    .bind(chrono::Utc::now())
+    .fetch_one(&mut *txn)
+    .await
+    .map_err(|e| format!("Failed to register expansion: {e}"))?;
+
+    for card in &expansion.cards {
+        for class in &card.class {
+            sqlx::query(
+                "INSERT INTO cards (expansion_id, number, class, name, rarity) VALUES (?,?,?,?,?) ON CONFLICT DO NOTHING",
+            )
+            .bind(id)
+            .bind(card.number as u32)
+            .bind(class.to_string())
+            .bind(card.name.clone())
+            .bind(card.rarity.to_string())
+            .execute(&mut *txn)
+            .await
+            .map_err(|e| format!("Failed to create expansion entries: {e}"))?;
+        }
+
+        store_card_details(&mut txn, id, card)
+            .await
+            .map_err(|e| format!("Failed to store details of card {}: {e}", card.number))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to register expansion: {e}"))?;
+
+    Ok(id)
+}
+
+/// Stores the manifest metadata of a card, replacing whatever was stored before.
+async fn store_card_details(
+    txn: &mut sqlx::Transaction<'_, Sqlite>,
+    expansion_id: u32,
+    card: &Pokemon,
+) -> Result<(), sqlx::Error> {
+    let mut stage = None;
+    let mut evolves_from = None;
+    let mut energy_type = None;
+    let mut hp = None;
+    let mut weakness = None;
+    let mut resistance = None;
+    let mut retreat = None;
+    let mut paradox_type = None;
+    let mut is_tera = None;
+    let mut ability = None;
+    let mut trainer_type = None;
+    let mut text = None;
+    let mut attacks: Vec<&Attack> = Vec::new();
+
+    match &card.details {
+        CardDetails::Pokemon(pokemon) => {
+            stage = Some(pokemon.stage.to_string());
+            evolves_from = pokemon.stage.evolves_from();
+            energy_type = pokemon.energy.map(|x| x.to_string());
+            hp = Some(pokemon.hp);
+            weakness = pokemon.weakness.map(|x| x.to_string());
+            resistance = pokemon.resistance.map(|x| x.to_string());
+            retreat = Some(pokemon.retreat);
+            paradox_type = pokemon.paradox_type.map(|x| x.to_string());
+            is_tera = Some(pokemon.is_tera);
+            ability = pokemon.ability.as_ref();
+            attacks.extend(&pokemon.attacks);
+        }
+        CardDetails::Trainer(trainer) => {
+            trainer_type = Some(trainer.trainer_type.to_string());
+            text = trainer.text.as_deref();
+            match &trainer.trainer_type {
+                TrainerType::Item {
+                    ability: item_ability,
+                    hp: item_hp,
+                } => {
+                    ability = item_ability.as_ref();
+                    hp = *item_hp;
+                }
+                TrainerType::Tool { attack } => attacks.extend(attack),
+                TrainerType::Supporter | TrainerType::Stadium => {}
+            }
+        }
+        CardDetails::Energy(EnergyCard::Special { text: energy_text }) => {
+            text = Some(energy_text);
+        }
+        CardDetails::Energy(EnergyCard::Basic) => {}
+    }
+
+    sqlx::query(
+        "
+        INSERT OR REPLACE INTO card_details
+            (expansion_id, number, image_url, regulation_mark, illustrator, supertype, stage, evolves_from, energy_type, hp, weakness, resistance, retreat, paradox_type, is_tera, ability_name, ability_effect, trainer_type, text)
+        VALUES
+            (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)
+        ",
+    )
+    .bind(expansion_id)
+    .bind(card.number as u32)
+    .bind(card.image_url.clone())
+    .bind(card.regulation_mark.clone())
+    .bind(card.illustrator.clone())
+    .bind(card.details.supertype())
+    .bind(stage)
+    .bind(evolves_from)
+    .bind(energy_type)
+    .bind(hp)
+    .bind(weakness)
+    .bind(resistance)
+    .bind(retreat)
+    .bind(paradox_type)
+    .bind(is_tera)
+    .bind(ability.map(|x| x.name.clone()))
+    .bind(ability.map(|x| x.effect.clone()))
+    .bind(trainer_type)
+    .bind(text)
+    .execute(&mut **txn)
+    .await?;
+
+    // INFO: INSERT OR REPLACE deletes the old row, which cascades to its attacks
+    for (position, attack) in attacks.into_iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO card_attacks (expansion_id, number, position, name, cost, effect, damage) VALUES (?,?,?,?,?,?,?)",
+        )
+        .bind(expansion_id)
+        .bind(card.number as u32)
+        .bind(position as u32)
+        .bind(attack.name.clone())
+        .bind(
+            attack
+                .cost
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .bind(attack.effect.clone())
+        .bind(attack.damage.map(|x| x.to_string()))
+        .execute(&mut **txn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn list(pool: &sqlx::Pool<Sqlite>) -> Result<Vec<RegisteredExpansion>, String> {
+    sqlx::query_as::<_, RegisteredExpansion>(
+        "
+        SELECT id, set_name, expansion_name, CAST(series_number AS REAL) AS series_number, printed_total, release_date, set_code, enabled, registered_at
+        FROM expansions
+        ORDER BY release_date, set_name, series_number
+        ",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list expansions: {e}"))
+}
+
+/// Loads the manifests of every expansion that should be scraped.
+pub async fn enabled_expansions(pool: &sqlx::Pool<Sqlite>) -> Result<Vec<Expansion>, ScrapeError> {
+    sqlx::query_as::<_, (u32, String)>(
+        "
+        SELECT id, manifest
+        FROM expansions
+        WHERE enabled
+          AND manifest IS NOT NULL
+        ORDER BY release_date, set_name, series_number
+        ",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ScrapeError::database("Failed to get expansions", e))?
+    .into_iter()
+    .map(|(id, manifest)| {
+        serde_json::from_str::<Expansion>(&manifest)
+            .map(|expansion| Expansion { id, ..expansion })
+            .map_err(|e| ScrapeError::Recoverable(format!("Failed to parse stored manifest: {e}")))
+    })
+    .collect()
+}
+
+/// Loads the manifest of a registered expansion, whether or not it's enabled.
+pub async fn get(pool: &sqlx::Pool<Sqlite>, id: u32) -> Result<Option<Expansion>, String> {
+    sqlx::query_as::<_, (String,)>(
+        "SELECT manifest FROM expansions WHERE id = ? AND manifest IS NOT NULL",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to get expansion: {e}"))?
+    .map(|(manifest,)| {
+        serde_json::from_str::<Expansion>(&manifest)
+            .map(|expansion| Expansion { id, ..expansion })
+            .map_err(|e| format!("Failed to parse stored manifest: {e}"))
+    })
+    .transpose()
+}
+
+/// Enables or disables scraping of an expansion without touching its history.
+///
+/// Returns `false` if the expansion isn't registered.
+pub async fn set_enabled(
+    pool: &sqlx::Pool<Sqlite>,
+    id: u32,
+    enabled: bool,
+) -> Result<bool, String> {
+    sqlx::query("UPDATE expansions SET enabled = ? WHERE id = ?")
+        .bind(enabled)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|x| x.rows_affected() > 0)
+        .map_err(|e| format!("Failed to update expansion: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn it_reads_every_bundled_manifest() {
+        let Ok(manifests) = read_directory("expansions") else {
+            panic!("Failed to read manifests");
+        };
+
+        assert_eq!(manifests.len(), 9);
+        assert!(manifests
+            .iter()
+            .all(|(_, expansion)| !expansion.cards.is_empty()));
+    }
+
+    #[test]
+    fn it_fails_on_a_missing_directory() {
+        assert!(read_directory("does_not_exist").is_err());
+    }
+}
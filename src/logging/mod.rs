@@ -0,0 +1,55 @@
+use tracing_subscriber::EnvFilter;
+
+/// Filter used when `RUST_LOG` isn't set.
+const DEFAULT_FILTER: &str = "info";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines, with the fields of every enclosing span.
+    #[default]
+    Text,
+    /// One JSON object per line, for shipping the docker logs somewhere they can be queried.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown log format {s}")),
+        }
+    }
+}
+
+/// Installs the global subscriber, filtering by the directives in `RUST_LOG`.
+pub fn init(format: LogFormat) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        // INFO: The span list carries the set, expansion, number and class of the card being
+        // scraped on every event, so one card's scrape can be picked out with a single filter
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_log_formats() {
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
@@ -1,13 +1,23 @@
 #![warn(missing_debug_implementations, rust_2018_idioms, rustdoc::all)]
 
-use card_scraper::{CardScaper, Expansion};
-use routes::{app_state::AppState, card, greet, list_cards};
+use card_scraper::{
+    schedule::Schedule, title_rules::TitleRules, CardScaper, Reloadable, ScrapeMode, ScraperConfig,
+};
+use routes::{app_state::AppState, card_page::card, greet, list_cards, metrics};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use thirtyfour::*;
 
 mod card_scraper;
 mod currency;
+mod expansion_registry;
+mod logging;
+mod monitoring;
+mod price_snapshots;
+mod rejected_listings;
 mod routes;
+mod scrape_failures;
+mod statistics;
+mod supply;
 
 async fn shutdown_signal() {
     let ctrl_c = async {
@@ -35,22 +45,13 @@ async fn shutdown_signal() {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let expansions = vec![
-        include_str!("../expansions/obsidian_flames.json"),
-        include_str!("../expansions/temporal_forces.json"),
-        include_str!("../expansions/shrouded_fable.json"),
-        include_str!("../expansions/stellar_crown.json"),
-        include_str!("../expansions/surging_sparks.json"),
-        include_str!("../expansions/journey_together.json"),
-        include_str!("../expansions/destined_rivals.json"),
-        include_str!("../expansions/mega_evolution.json"),
-        include_str!("../expansions/phantasmal_flames.json"),
-    ];
-
-    let expansions = expansions
-        .into_iter()
-        .map(serde_json::from_str::<Expansion>)
-        .collect::<Result<Vec<_>, _>>()?;
+    logging::init(
+        std::env::var("LOG_FORMAT")
+            .map(|x| x.parse::<logging::LogFormat>())
+            .unwrap_or(Ok(logging::LogFormat::default()))?,
+    );
+
+    let metrics_handle = monitoring::install()?;
 
     let connection_options = SqliteConnectOptions::new()
         .filename("db/demo.db")
@@ -63,11 +64,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     sqlx::migrate!("db/migrations").run(&pool).await?;
 
+    let expansions_dir = std::env::var("EXPANSIONS_DIR").unwrap_or("expansions".into());
+    expansion_registry::load_directory(&pool, expansions_dir).await?;
+
+    let fx_rates =
+        std::path::PathBuf::from(std::env::var("FX_RATES").unwrap_or("fx_rates.csv".into()));
+    if fx_rates.exists() {
+        let n = currency::fx::load_csv(&pool, &fx_rates).await?;
+        tracing::info!(rates = n, path = %fx_rates.display(), "Loaded exchange rates");
+    }
+    let reporting_currency = std::env::var("REPORTING_CURRENCY")
+        .unwrap_or("GBP".into())
+        .parse::<&currency::Currency>()?;
+
     let wd_url = std::env::var("WEB_DRIVER_URL").unwrap_or("http://localhost:4444".into());
     let sleep_secs = std::env::var("SCRAPER_SLEEP_SECS")
         .unwrap_or("20".into())
         .parse::<u64>()
         .expect("Failed to parse SCRAPER_SLEEP_SECS");
+    let sources = std::env::var("MARKETPLACE_SOURCES")
+        .unwrap_or(card_scraper::source::ebay::EBAY_UK_ID.into())
+        .split(',')
+        .map(|id| {
+            card_scraper::source::from_id(id.trim())
+                .ok_or(format!("Unknown marketplace source {id}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let modes = std::env::var("SCRAPE_MODES")
+        .unwrap_or("sold".into())
+        .split(',')
+        .map(|mode| mode.trim().parse::<ScrapeMode>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let fences = std::env::var("OUTLIER_FENCE")
+        .map(|x| x.parse::<statistics::Fences>())
+        .unwrap_or(Ok(statistics::Fences::default()))?;
+    let snapshot_listings = std::env::var("SNAPSHOT_LISTINGS")
+        .unwrap_or("30".into())
+        .parse::<u32>()
+        .expect("Failed to parse SNAPSHOT_LISTINGS");
+    let retry = card_scraper::retry::RetryPolicy {
+        retries: std::env::var("SCRAPE_RETRIES")
+            .unwrap_or("3".into())
+            .parse::<u32>()
+            .expect("Failed to parse SCRAPE_RETRIES"),
+        base_delay: std::time::Duration::from_secs(
+            std::env::var("SCRAPE_RETRY_DELAY_SECS")
+                .unwrap_or("10".into())
+                .parse::<u64>()
+                .expect("Failed to parse SCRAPE_RETRY_DELAY_SECS"),
+        ),
+    };
     let mut caps = DesiredCapabilities::chrome();
     caps.add_arg("--start-maximized")?;
     caps.add_arg("--disable-dev-shm-usage")?;
@@ -78,23 +124,159 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let shutdown = std::sync::Arc::new(tokio::sync::Notify::new());
     let shutdown_scraper = shutdown.clone();
     let shutdown_server = shutdown.clone();
+    let shutdown_snapshots = shutdown.clone();
+    let scraper_control = std::sync::Arc::new(card_scraper::control::ScraperControl::default());
 
-    let scraper = CardScaper::new(pool.clone(), wd_url, caps, shutdown_scraper, sleep_secs);
+    let scraper = CardScaper::new(
+        pool.clone(),
+        wd_url,
+        caps,
+        shutdown_scraper,
+        scraper_control.clone(),
+        ScraperConfig {
+            sleep_seconds: sleep_secs,
+            sources,
+            modes,
+            title_rules: Reloadable::load(
+                std::env::var("TITLE_RULES").unwrap_or("title_rules.toml".into()),
+                |x| TitleRules::load(x),
+            )?,
+            schedule: Reloadable::load(
+                std::env::var("SCHEDULE").unwrap_or("schedule.toml".into()),
+                |x| Schedule::load(x),
+            )?,
+            retry,
+            artifacts_dir: std::env::var("FAILURE_ARTIFACTS_DIR")
+                .unwrap_or("screenshots".into())
+                .into(),
+        },
+    );
 
     let h = tokio::spawn(async move {
-        let a = scraper.start_scraping_expansions(expansions).await;
+        let a = scraper.start_scraping_expansions().await;
         scraper_tx.send(()).expect("Failed to send scraper signal");
         a
     });
 
-    let api_routes = axum::Router::new().route("/", axum::routing::get(routes::api::say_hello));
+    let snapshot_job = price_snapshots::SnapshotJob::new(
+        pool.clone(),
+        shutdown_snapshots,
+        snapshot_listings,
+        fences,
+        reporting_currency,
+    );
+    let snapshots = tokio::spawn(async move { snapshot_job.run().await });
+
+    let api_routes = axum::Router::new()
+        .nest("/v1", routes::api::v1::router())
+        .fallback(routes::api::not_found);
+
+    let admin_routes = axum::Router::new()
+        .route(
+            "/expansions",
+            axum::routing::get(routes::admin::list_expansions)
+                .post(routes::admin::register_expansion),
+        )
+        .route(
+            "/expansions/{id}/enable",
+            axum::routing::post(routes::admin::enable_expansion),
+        )
+        .route(
+            "/expansions/{id}/disable",
+            axum::routing::post(routes::admin::disable_expansion),
+        )
+        .route(
+            "/listings/regrade",
+            axum::routing::post(routes::admin::regrade_listings),
+        )
+        .route(
+            "/scraper",
+            axum::routing::get(routes::admin::scraper_status),
+        )
+        .route(
+            "/scraper/pause",
+            axum::routing::post(routes::admin::pause_scraper),
+        )
+        .route(
+            "/scraper/resume",
+            axum::routing::post(routes::admin::resume_scraper),
+        )
+        .route(
+            "/scraper/trigger",
+            axum::routing::post(routes::admin::trigger_scraper),
+        )
+        .route(
+            "/scraper/queue",
+            axum::routing::post(routes::admin::queue_scrape),
+        )
+        .route(
+            "/scrape_failures",
+            axum::routing::get(routes::admin::list_scrape_failures),
+        )
+        .route(
+            "/scrape_failures/{id}/screenshot",
+            axum::routing::get(routes::admin::scrape_failure_screenshot),
+        )
+        .route(
+            "/scrape_failures/{id}/page_source",
+            axum::routing::get(routes::admin::scrape_failure_page_source),
+        )
+        .route(
+            "/fx_rates",
+            axum::routing::get(routes::admin::list_fx_rates).post(routes::admin::store_fx_rates),
+        )
+        .route(
+            "/rejected_listings",
+            axum::routing::get(routes::admin::list_rejected_listings),
+        )
+        .route(
+            "/rejected_listings/{id}/accept",
+            axum::routing::post(routes::admin::accept_rejected_listing),
+        )
+        .route(
+            "/rejected_listings/{id}/confirm",
+            axum::routing::post(routes::admin::confirm_rejected_listing),
+        );
 
     let app = axum::Router::new()
         .nest("/api", api_routes)
+        .nest("/admin", admin_routes)
+        .route("/metrics", axum::routing::get(metrics))
         .route("/greet/{name}", axum::routing::get(greet))
         .route("/", axum::routing::get(list_cards))
-        .route("/{expansion}/{number}/{class}", axum::routing::get(card))
-        .with_state(AppState { pool });
+        .route(
+            "/cards/{expansion_id}/{number}/{class}",
+            axum::routing::get(card),
+        )
+        .layer(axum::middleware::from_fn(monitoring::track_requests))
+        .layer(
+            tower_http::trace::TraceLayer::new_for_http()
+                .make_span_with(|request: &axum::extract::Request| {
+                    // INFO: Spans are named after the matched route so that requests for
+                    // different cards can still be grouped together
+                    let route = request
+                        .extensions()
+                        .get::<axum::extract::MatchedPath>()
+                        .map(|x| x.as_str().to_owned());
+
+                    tracing::info_span!(
+                        "http",
+                        method = %request.method(),
+                        uri = %request.uri(),
+                        route,
+                    )
+                })
+                .on_response(
+                    tower_http::trace::DefaultOnResponse::new().level(tracing::Level::INFO),
+                ),
+        )
+        .with_state(AppState {
+            pool,
+            fences,
+            reporting_currency,
+            metrics: metrics_handle,
+            scraper: scraper_control,
+        });
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     let server = axum::serve(listener, app).with_graceful_shutdown(async move {
@@ -112,20 +294,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tokio::select! {
         _ = shutdown_signal() => {
-            println!("Shutdown signal received");
+            tracing::info!("Shutdown signal received");
             shutdown.notify_waiters();
         }
         _ = a.changed() => {
-            println!("Scraper completed");
+            tracing::info!("Scraper completed");
             shutdown.notify_waiters();
         }
         _ = b.changed() => {
-            println!("Server completed");
+            tracing::info!("Server completed");
             shutdown.notify_waiters();
         }
     }
 
-    let (h, server, ..) = tokio::join!(h, server, scraper_rx.changed(), server_rx.changed());
+    let (h, server, snapshots, ..) = tokio::join!(
+        h,
+        server,
+        snapshots,
+        scraper_rx.changed(),
+        server_rx.changed()
+    );
+
+    snapshots.map_err(|e| e.to_string())?;
 
     match h {
         Err(e) => Err(e.to_string()),
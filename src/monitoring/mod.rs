@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+
+pub const CARDS_SCRAPED: &str = "scraper_cards_scraped_total";
+pub const PAGES_VISITED: &str = "scraper_pages_visited_total";
+pub const LISTINGS_FOUND: &str = "scraper_listings_found_total";
+pub const LISTINGS_ACCEPTED: &str = "scraper_listings_accepted_total";
+pub const LISTINGS_REJECTED: &str = "scraper_listings_rejected_total";
+pub const PARSE_FAILURES: &str = "scraper_parse_failures_total";
+pub const WEBDRIVER_ERRORS: &str = "scraper_webdriver_errors_total";
+pub const CARD_FAILURES: &str = "scraper_card_failures_total";
+pub const CARD_DURATION: &str = "scraper_card_duration_seconds";
+pub const EXPANSION_DURATION: &str = "scraper_expansion_duration_seconds";
+pub const LAST_SUCCESSFUL_CYCLE: &str = "scraper_last_successful_cycle_timestamp_seconds";
+pub const SECONDS_SINCE_SUCCESSFUL_CYCLE: &str = "scraper_seconds_since_last_successful_cycle";
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+
+/// Buckets for scrape durations, from a card with a single page of results up to a large
+/// expansion.
+const SCRAPE_BUCKETS: [f64; 12] = [
+    1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0, 7200.0,
+];
+const HTTP_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Unix timestamp of when no cards were last due, zero until the scraper has caught up.
+static LAST_CYCLE: AtomicI64 = AtomicI64::new(0);
+
+fn builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Prefix("scraper_".into()), &SCRAPE_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Prefix("http_".into()), &HTTP_BUCKETS)
+}
+
+/// Installs the global metrics recorder, returning the handle used to render `/metrics`.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = builder()?.install_recorder()?;
+    describe();
+    Ok(handle)
+}
+
+fn describe() {
+    metrics::describe_counter!(
+        CARDS_SCRAPED,
+        "Cards whose listings were scraped and stored"
+    );
+    metrics::describe_counter!(PAGES_VISITED, "Results pages loaded");
+    metrics::describe_counter!(LISTINGS_FOUND, "Listings on the results pages");
+    metrics::describe_counter!(LISTINGS_ACCEPTED, "Listings that passed every filter");
+    metrics::describe_counter!(LISTINGS_REJECTED, "Listings rejected, by reason");
+    metrics::describe_counter!(PARSE_FAILURES, "Listings that failed to parse, by kind");
+    metrics::describe_counter!(WEBDRIVER_ERRORS, "Failed WebDriver calls");
+    metrics::describe_counter!(CARD_FAILURES, "Failed attempts at scraping a card");
+    metrics::describe_histogram!(
+        CARD_DURATION,
+        metrics::Unit::Seconds,
+        "Time to scrape a card"
+    );
+    metrics::describe_histogram!(
+        EXPANSION_DURATION,
+        metrics::Unit::Seconds,
+        "Time spent scraping an expansion's cards in a batch of due cards"
+    );
+    metrics::describe_gauge!(
+        LAST_SUCCESSFUL_CYCLE,
+        metrics::Unit::Seconds,
+        "When the scraper last caught up with its schedule"
+    );
+    metrics::describe_gauge!(
+        SECONDS_SINCE_SUCCESSFUL_CYCLE,
+        metrics::Unit::Seconds,
+        "Time since the scraper last caught up with its schedule"
+    );
+    metrics::describe_histogram!(
+        HTTP_REQUEST_DURATION,
+        metrics::Unit::Seconds,
+        "Latency of HTTP requests, by route"
+    );
+}
+
+/// Renders every metric in the Prometheus text format.
+pub fn render(handle: &PrometheusHandle) -> String {
+    // INFO: Computed on scrape rather than kept up to date so that a stuck scraper still shows a
+    // growing value
+    let last_cycle = LAST_CYCLE.load(Ordering::Relaxed);
+    if last_cycle > 0 {
+        metrics::gauge!(SECONDS_SINCE_SUCCESSFUL_CYCLE)
+            .set((chrono::Utc::now().timestamp() - last_cycle) as f64);
+    }
+
+    handle.render()
+}
+
+/// Records that no cards are due, so every enabled expansion is up to date.
+pub fn cycle_completed() {
+    let now = chrono::Utc::now().timestamp();
+    LAST_CYCLE.store(now, Ordering::Relaxed);
+    metrics::gauge!(LAST_SUCCESSFUL_CYCLE).set(now as f64);
+}
+
+/// Middleware recording the latency of every request against the route that handled it.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = std::time::Instant::now();
+    let method = request.method().to_string();
+    // INFO: Labelled with the route rather than the path so that every card doesn't get its own
+    // series
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|x| x.as_str().to_owned())
+        .unwrap_or("unmatched".into());
+
+    let response = next.run(request).await;
+
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION,
+        "method" => method,
+        "route" => route,
+        "status" => response.status().as_u16().to_string(),
+    )
+    .record(start.elapsed());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_renders_histograms_with_buckets() {
+        let Ok(builder) = builder() else {
+            panic!("Failed to configure buckets");
+        };
+        let recorder = builder.build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::histogram!(CARD_DURATION, "expansion" => "DRI").record(42.0);
+            metrics::histogram!(HTTP_REQUEST_DURATION, "route" => "/").record(0.02);
+        });
+
+        let rendered = handle.render();
+        assert!(
+            rendered.contains(r#"scraper_card_duration_seconds_bucket{expansion="DRI",le="60"} 1"#)
+        );
+        assert!(
+            rendered.contains(r#"http_request_duration_seconds_bucket{route="/",le="0.025"} 1"#)
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, sqlite::Sqlite};
+
+use crate::{
+    currency::{fx::ExchangeRates, Currency, Money},
+    statistics::{Fences, Summary},
+};
+
+/// Which price of a listing statistics are computed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceBasis {
+    /// The price of the card alone.
+    #[default]
+    Item,
+    /// The price of the card including its share of postage, leaving out listings that didn't
+    /// say what postage cost.
+    Delivered,
+}
+
+impl PriceBasis {
+    pub const ALL: [Self; 2] = [Self::Item, Self::Delivered];
+
+    /// Basis stored in `price_snapshots.basis`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Item => "item",
+            Self::Delivered => "delivered",
+        }
+    }
+
+    /// Column of `listings` holding the price of one card on this basis.
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::Item => "unit_price",
+            Self::Delivered => "delivered_unit_price",
+        }
+    }
+}
+
+impl std::fmt::Display for PriceBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl std::str::FromStr for PriceBasis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|x| x.code() == s).ok_or(format!(
+            "Invalid price basis {s}, expected item or delivered"
+        ))
+    }
+}
+
+impl sqlx::Type<Sqlite> for PriceBasis {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <&str as sqlx::Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for PriceBasis {
+    fn encode_by_ref(
+        &self,
+        args: &mut Vec<sqlx::sqlite::SqliteArgumentValue<'q>>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <&str as sqlx::Encode<Sqlite>>::encode_by_ref(&self.code(), args)
+    }
+}
+
+impl sqlx::Decode<'_, Sqlite> for PriceBasis {
+    fn decode(value: sqlx::sqlite::SqliteValueRef<'_>) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(<&str as sqlx::Decode<Sqlite>>::decode(value)?.parse()?)
+    }
+}
+
+/// Statistics of a card variant's recent sold prices on a given day.
+///
+/// Prices are per card, in minor units of `currency` and, apart from the counts, are computed after
+/// removing outliers.
+#[derive(Debug, Serialize, FromRow, Clone, PartialEq)]
+pub struct PriceSnapshot {
+    pub card_expansion_id: u32,
+    pub card_number: u32,
+    pub card_class: String,
+    pub date: NaiveDate,
+    pub graded: bool,
+    pub basis: PriceBasis,
+    pub sample_count: u32,
+    pub outlier_count: u32,
+    pub median: u32,
+    pub trimmed_mean: u32,
+    pub min: u32,
+    pub max: u32,
+    pub currency: String,
+}
+
+#[derive(Debug, FromRow)]
+struct SnapshotListing {
+    card_expansion_id: u32,
+    card_number: u32,
+    card_class: String,
+    graded: bool,
+    #[sqlx(flatten)]
+    price: Money,
+}
+
+/// Computes one snapshot per card variant and grading state from a set of listings.
+///
+/// Listings are converted to `currency` first, and those that can't be converted are left out.
+fn summarise(
+    date: NaiveDate,
+    basis: PriceBasis,
+    listings: Vec<SnapshotListing>,
+    fences: &Fences,
+    currency: &'static Currency,
+    rates: &ExchangeRates,
+) -> Vec<PriceSnapshot> {
+    listings
+        .into_iter()
+        .fold(BTreeMap::<_, Vec<Money>>::new(), |mut acc, x| {
+            match x.price.convert(currency, rates) {
+                Ok(price) => acc
+                    .entry((x.card_expansion_id, x.card_number, x.card_class, x.graded))
+                    .or_default()
+                    .push(price),
+                Err(e) => tracing::warn!(error = %e, "Leaving listing out of price snapshot"),
+            }
+
+            acc
+        })
+        .into_iter()
+        .filter_map(
+            |((card_expansion_id, card_number, card_class, graded), prices)| {
+                let summary = match Summary::of(prices, fences) {
+                    Ok(summary) => summary?,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to summarise prices");
+                        return None;
+                    }
+                };
+                // INFO: Stored prices are u32, so a summary of them fits too
+                let minor = |x: Money| u32::try_from(x.amount()).ok();
+
+                Some(PriceSnapshot {
+                    card_expansion_id,
+                    card_number,
+                    card_class,
+                    date,
+                    graded,
+                    basis,
+                    sample_count: summary.count as u32,
+                    outlier_count: summary.outliers as u32,
+                    median: minor(summary.median())?,
+                    trimmed_mean: minor(summary.mean)?,
+                    min: minor(summary.min)?,
+                    max: minor(summary.max)?,
+                    currency: currency.code().into(),
+                })
+            },
+        )
+        .collect()
+}
+
+/// Snapshots the prices of every card variant from its `window` most recent listings in the
+/// reporting currency, replacing any snapshot already taken on `date`.
+///
+/// Returns the number of snapshots written.
+pub async fn take_snapshot(
+    pool: &sqlx::Pool<Sqlite>,
+    date: NaiveDate,
+    basis: PriceBasis,
+    window: u32,
+    fences: &Fences,
+    currency: &'static Currency,
+) -> Result<usize, String> {
+    let listings = sqlx::query_as::<_, SnapshotListing>(&format!(
+        "
+        SELECT card_expansion_id, card_number, card_class, graded_by IS NOT NULL AS graded, {0} AS price, currency
+        FROM ranked_listings
+        WHERE listing_rank <= ?
+          AND {0} IS NOT NULL
+        ",
+        basis.column()
+    ))
+    .bind(window)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to get listings: {e}"))?;
+
+    let rates = ExchangeRates::latest(pool).await?;
+    let snapshots = summarise(date, basis, listings, fences, currency, &rates);
+
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error creating transaction: {e}"))?;
+
+    for snapshot in &snapshots {
+        sqlx::query(
+            "
+            INSERT OR REPLACE INTO price_snapshots
+                (card_expansion_id, card_number, card_class, date, graded, basis, sample_count, outlier_count, median, trimmed_mean, min, max, currency)
+            VALUES
+                (?,?,?,?,?,?,?,?,?,?,?,?,?)
+            ",
+        )
+        .bind(snapshot.card_expansion_id)
+        .bind(snapshot.card_number)
+        .bind(snapshot.card_class.clone())
+        .bind(snapshot.date)
+        .bind(snapshot.graded)
+        .bind(snapshot.basis)
+        .bind(snapshot.sample_count)
+        .bind(snapshot.outlier_count)
+        .bind(snapshot.median)
+        .bind(snapshot.trimmed_mean)
+        .bind(snapshot.min)
+        .bind(snapshot.max)
+        .bind(snapshot.currency.clone())
+        .execute(&mut *txn)
+        .await
+        .map_err(|e| format!("Failed to insert price snapshot: {e}"))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit price snapshots: {e}"))?;
+
+    Ok(snapshots.len())
+}
+
+/// How long to wait before retrying after a snapshot failed.
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Takes a snapshot once per day, shortly after midnight UTC, until shutdown.
+///
+/// A snapshot of each basis is also taken on start if none was taken today, so restarts don't
+/// leave gaps.
+#[derive(Debug)]
+pub struct SnapshotJob {
+    pool: sqlx::Pool<Sqlite>,
+    shutdown_rx: std::sync::Arc<tokio::sync::Notify>,
+    window: u32,
+    fences: Fences,
+    currency: &'static Currency,
+}
+
+impl SnapshotJob {
+    pub fn new(
+        pool: sqlx::Pool<Sqlite>,
+        shutdown_rx: std::sync::Arc<tokio::sync::Notify>,
+        window: u32,
+        fences: Fences,
+        currency: &'static Currency,
+    ) -> Self {
+        Self {
+            pool,
+            shutdown_rx,
+            window,
+            fences,
+            currency,
+        }
+    }
+
+    pub async fn run(&self) {
+        loop {
+            tokio::select! {
+                _ = self.shutdown_rx.notified() => {
+                    tracing::info!("Killing price snapshots");
+                    return;
+                }
+                _ = self.snapshot_and_sleep() => {}
+            }
+        }
+    }
+
+    async fn snapshot_and_sleep(&self) {
+        let today = chrono::Utc::now().date_naive();
+        let mut failed = false;
+
+        for basis in PriceBasis::ALL {
+            match self.has_snapshot(today, basis).await {
+                Ok(true) => {}
+                Ok(false) => match take_snapshot(
+                    &self.pool,
+                    today,
+                    basis,
+                    self.window,
+                    &self.fences,
+                    self.currency,
+                )
+                .await
+                {
+                    Ok(n) => tracing::info!("Took {n} {basis} price snapshots for {today}"),
+                    Err(e) => {
+                        tracing::error!("Failed to take {basis} price snapshots: {e}");
+                        failed = true;
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("{e}");
+                    failed = true;
+                }
+            }
+        }
+
+        // INFO: Failures are retried shortly rather than tomorrow, so a transient error doesn't
+        // lose the day's snapshot
+        let next_run = match failed {
+            true => Some(RETRY_INTERVAL),
+            false => today
+                .succ_opt()
+                .and_then(|x| x.and_hms_opt(0, 5, 0))
+                .map(|x| x.and_utc() - chrono::Utc::now())
+                .and_then(|x| x.to_std().ok()),
+        }
+        .unwrap_or(std::time::Duration::from_secs(60 * 60));
+
+        tokio::time::sleep(next_run).await;
+    }
+
+    async fn has_snapshot(&self, date: NaiveDate, basis: PriceBasis) -> Result<bool, String> {
+        sqlx::query_as::<_, (bool,)>(
+            "SELECT EXISTS (SELECT 1 FROM price_snapshots WHERE date = ? AND basis = ?)",
+        )
+        .bind(date)
+        .bind(basis)
+        .fetch_one(&self.pool)
+        .await
+        .map(|(x,)| x)
+        .map_err(|e| format!("Failed to check {basis} price snapshots: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::currency;
+
+    fn listing(number: u32, graded: bool, price: u64) -> SnapshotListing {
+        SnapshotListing {
+            card_expansion_id: 1,
+            card_number: number,
+            card_class: "Regular".into(),
+            graded,
+            price: Money::new(price, currency::GBP),
+        }
+    }
+
+    #[test]
+    fn it_snapshots_graded_and_raw_listings_separately() {
+        let Some(date) = NaiveDate::from_ymd_opt(2025, 1, 1) else {
+            panic!("Invalid date");
+        };
+        let listings = vec![
+            listing(1, false, 100),
+            listing(1, false, 200),
+            listing(1, false, 300),
+            listing(1, false, 50_000),
+            listing(1, true, 4_000),
+            listing(2, false, 25),
+        ];
+
+        let snapshots = summarise(
+            date,
+            PriceBasis::Item,
+            listings,
+            &Fences::default(),
+            currency::GBP,
+            &ExchangeRates::default(),
+        );
+
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(
+            snapshots[0],
+            PriceSnapshot {
+                card_expansion_id: 1,
+                card_number: 1,
+                card_class: "Regular".into(),
+                date,
+                graded: false,
+                basis: PriceBasis::Item,
+                sample_count: 3,
+                outlier_count: 1,
+                median: 200,
+                trimmed_mean: 200,
+                min: 100,
+                max: 300,
+                currency: "GBP".into(),
+            }
+        );
+        assert!(snapshots[1].graded);
+        assert_eq!(snapshots[1].median, 4_000);
+        assert_eq!(snapshots[2].card_number, 2);
+    }
+
+    #[test]
+    fn it_converts_listings_to_the_reporting_currency() {
+        let Some(date) = NaiveDate::from_ymd_opt(2025, 1, 1) else {
+            panic!("Invalid date");
+        };
+        let Ok(rates) = "date,base,quote,rate\n2025-01-01,GBP,USD,1.25".parse::<ExchangeRates>()
+        else {
+            panic!("Failed to parse rates");
+        };
+        let mut usd = listing(1, false, 0);
+        usd.price = Money::new(500, currency::USD);
+        let mut jpy = listing(1, false, 0);
+        jpy.price = Money::new(1_000, currency::JPY);
+
+        let snapshots = summarise(
+            date,
+            PriceBasis::Delivered,
+            vec![listing(1, false, 300), usd, jpy],
+            &Fences::default(),
+            currency::GBP,
+            &rates,
+        );
+
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].sample_count, 2);
+        assert_eq!(snapshots[0].median, 350);
+        assert_eq!(snapshots[0].basis, PriceBasis::Delivered);
+    }
+
+    #[test]
+    fn it_parses_price_bases() {
+        assert_eq!("item".parse(), Ok(PriceBasis::Item));
+        assert_eq!("delivered".parse(), Ok(PriceBasis::Delivered));
+        assert!("total".parse::<PriceBasis>().is_err());
+        assert_eq!(PriceBasis::Delivered.column(), "delivered_unit_price");
+    }
+}
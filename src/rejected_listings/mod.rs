@@ -0,0 +1,428 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::{prelude::FromRow, sqlite::Sqlite, SqliteConnection};
+
+use crate::{
+    card_scraper::{grading::Grade, lots::LotDetector},
+    currency::{Currency, Money},
+};
+
+/// Why a scraped listing wasn't stored as a sale of the card.
+///
+/// Stored in `rejected_listings.reason` as one of `title_rule`, `invalid_price`, `price_range` or
+/// `parse_error`, see [`RejectionReason::code`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// A title rule, named by the field, rejected the title.
+    TitleRule(String),
+    /// The price couldn't be parsed.
+    InvalidPrice,
+    /// The price was a range, i.e. "£5.00 to £12.00", so what the listing sold for is unknown.
+    PriceRange,
+    /// Another part of the listing couldn't be parsed, with the kind of failure, i.e.
+    /// "invalid_date".
+    ParseError(&'static str),
+}
+
+impl RejectionReason {
+    /// Machine-readable reason stored in `rejected_listings.reason`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TitleRule(_) => "title_rule",
+            Self::InvalidPrice => "invalid_price",
+            Self::PriceRange => "price_range",
+            Self::ParseError(_) => "parse_error",
+        }
+    }
+
+    pub fn rule(&self) -> Option<&str> {
+        match self {
+            Self::TitleRule(rule) => Some(rule),
+            Self::InvalidPrice | Self::PriceRange | Self::ParseError(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TitleRule(rule) => write!(f, "title_rule ({rule})"),
+            Self::ParseError(kind) => write!(f, "parse_error ({kind})"),
+            reason => write!(f, "{}", reason.code()),
+        }
+    }
+}
+
+/// A sold listing that was scraped for a card but rejected.
+///
+/// Fields that couldn't be parsed are `None`, and such listings can only have their rejection
+/// confirmed.
+#[derive(Debug)]
+pub struct Rejection {
+    pub source: &'static str,
+    pub listing_id: Option<i64>,
+    pub title: String,
+    pub link: String,
+    pub date: Option<NaiveDate>,
+    pub price: Option<Money>,
+    /// `None` when the listing didn't say, and zero when postage was free.
+    pub postage: Option<Money>,
+    /// Price as shown by the marketplace.
+    pub raw_price: Option<String>,
+    pub bids: Option<u32>,
+    pub accepts_offers: Option<bool>,
+    pub offer_was_accepted: Option<bool>,
+    pub grade: Option<Grade>,
+    pub reason: RejectionReason,
+}
+
+/// Stores a rejection against the card that was being searched for, ignoring listings that were
+/// already rejected for it.
+pub async fn record(
+    conn: &mut SqliteConnection,
+    rejection: &Rejection,
+    (expansion_id, number, class): (u32, u32, &str),
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        INSERT INTO rejected_listings
+            (source, listing_id, title, link, date, price, raw_price, currency, postage, bids, accepts_offers, offer_was_accepted, graded_by, grade, grade_qualifier, card_expansion_id, card_number, card_class, reason, rule, rejected_at)
+        VALUES
+            (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)
+        ON CONFLICT DO NOTHING
+        ",
+    )
+    .bind(rejection.source)
+    .bind(rejection.listing_id)
+    .bind(rejection.title.clone())
+    .bind(rejection.link.clone())
+    .bind(rejection.date)
+    .bind(rejection.price)
+    .bind(rejection.raw_price.clone())
+    .bind(rejection.price.map(|x| x.currency()))
+    .bind(rejection.postage)
+    .bind(rejection.bids)
+    .bind(rejection.accepts_offers)
+    .bind(rejection.offer_was_accepted)
+    .bind(rejection.grade.as_ref().map(|x| x.company_id))
+    .bind(rejection.grade.as_ref().and_then(|x| x.grade))
+    .bind(
+        rejection
+            .grade
+            .as_ref()
+            .and_then(|x| x.qualifier)
+            .map(|x| x.to_string()),
+    )
+    .bind(expansion_id)
+    .bind(number)
+    .bind(class)
+    .bind(rejection.reason.code())
+    .bind(rejection.reason.rule())
+    .bind(chrono::Utc::now())
+    .execute(conn)
+    .await
+    .map(|_| ())
+}
+
+/// Summary of a stored rejection for the review page.
+#[derive(Debug, Serialize, FromRow)]
+pub struct RejectedListing {
+    pub id: u32,
+    pub source: String,
+    pub listing_id: Option<i64>,
+    pub title: String,
+    pub link: String,
+    pub date: Option<NaiveDate>,
+    pub price: Option<u32>,
+    pub raw_price: Option<String>,
+    pub currency: Option<&'static Currency>,
+    pub card_expansion_id: u32,
+    pub card_set_code: Option<String>,
+    pub card_expansion_name: String,
+    pub card_number: u32,
+    pub card_class: String,
+    pub card_name: String,
+    pub reason: String,
+    pub rule: Option<String>,
+    pub status: String,
+    pub rejected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Lists rejections with a review status, newest first, alongside the number of matches.
+pub async fn list(
+    pool: &sqlx::Pool<Sqlite>,
+    status: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<(Vec<RejectedListing>, u32), String> {
+    let (total,) =
+        sqlx::query_as::<_, (u32,)>("SELECT COUNT(*) FROM rejected_listings WHERE status = ?")
+            .bind(status)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Failed to count rejected listings: {e}"))?;
+
+    let rejections = sqlx::query_as::<_, RejectedListing>(
+        "
+        SELECT
+            rejected_listings.id,
+            rejected_listings.source,
+            rejected_listings.listing_id,
+            rejected_listings.title,
+            rejected_listings.link,
+            rejected_listings.date,
+            rejected_listings.price,
+            rejected_listings.raw_price,
+            rejected_listings.currency,
+            rejected_listings.card_expansion_id,
+            expansions.set_code AS card_set_code,
+            expansions.expansion_name AS card_expansion_name,
+            rejected_listings.card_number,
+            rejected_listings.card_class,
+            cards.name AS card_name,
+            rejected_listings.reason,
+            rejected_listings.rule,
+            rejected_listings.status,
+            rejected_listings.rejected_at
+        FROM
+            rejected_listings
+            JOIN cards ON cards.expansion_id = rejected_listings.card_expansion_id
+            AND cards.number = rejected_listings.card_number
+            AND cards.class = rejected_listings.card_class
+            JOIN expansions ON expansions.id = rejected_listings.card_expansion_id
+        WHERE
+            rejected_listings.status = ?
+        ORDER BY
+            rejected_listings.rejected_at DESC,
+            rejected_listings.id DESC
+        LIMIT ? OFFSET ?
+        ",
+    )
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list rejected listings: {e}"))?;
+
+    Ok((rejections, total))
+}
+
+/// Result of reviewing a rejection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Review {
+    Reviewed,
+    /// The rejection doesn't exist or was already reviewed.
+    NotPending,
+    /// The listing is missing its id, date or price so can't be stored as a sale.
+    Incomplete,
+    /// The listing is already stored as a sale of the card.
+    Duplicate,
+}
+
+/// Moves a pending rejection into `listings` and `listings_cards`.
+pub async fn accept(pool: &sqlx::Pool<Sqlite>, id: u32) -> Result<Review, String> {
+    let mut txn = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error creating transaction: {e}"))?;
+
+    let Some((complete, title, price, postage, currency)) = sqlx::query_as::<
+        _,
+        (bool, String, Option<u32>, Option<u32>, Option<&'static Currency>),
+    >(
+        "
+        SELECT listing_id IS NOT NULL AND date IS NOT NULL AND price IS NOT NULL AND currency IS NOT NULL, title, price, postage, currency
+        FROM rejected_listings
+        WHERE id = ? AND status = 'pending'
+        ",
+    )
+    .bind(id)
+    .fetch_optional(&mut *txn)
+    .await
+    .map_err(|e| format!("Failed to get rejected listing: {e}"))?
+    else {
+        return Ok(Review::NotPending);
+    };
+
+    let Some(currency) = currency.filter(|_| complete) else {
+        return Ok(Review::Incomplete);
+    };
+    let Some(price) = price.map(|x| Money::new(x.into(), currency)) else {
+        return Ok(Review::Incomplete);
+    };
+    let postage = postage.map(|x| Money::new(x.into(), currency));
+    let lot = LotDetector::default().detect(&title);
+
+    let r: Result<bool, sqlx::Error> = async {
+        // INFO: The listing may already be stored as a sale of another card, i.e. when it was
+        // scraped for that card too, in which case it's only linked to this one
+        sqlx::query(
+            "
+            INSERT INTO listings
+                (id, title, date, price, currency, postage, link, bids, accepts_offers, offer_was_accepted, graded_by, grade, grade_qualifier, lot_kind, quantity, unit_price, delivered_unit_price, source)
+            SELECT
+                listing_id, title, date, price, currency, postage, link, bids, accepts_offers, offer_was_accepted, graded_by, grade, grade_qualifier, ?, ?, ?, ?, source
+            FROM rejected_listings
+            WHERE id = ?
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(lot.kind())
+        .bind(lot.quantity())
+        .bind(lot.unit_price(price))
+        .bind(lot.delivered_unit_price(price, postage))
+        .bind(id)
+        .execute(&mut *txn)
+        .await?;
+
+        let linked = sqlx::query(
+            "
+            INSERT INTO listings_cards
+                (listing_id, card_expansion_id, card_number, card_class)
+            SELECT
+                listing_id, card_expansion_id, card_number, card_class
+            FROM rejected_listings
+            WHERE id = ?
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(id)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+
+        // INFO: The rejection stays pending rather than being accepted without storing anything
+        if linked == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE rejected_listings SET status = 'accepted', reviewed_at = ? WHERE id = ?",
+        )
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .execute(&mut *txn)
+        .await?;
+
+        Ok(true)
+    }
+    .await;
+
+    let inserted = match r {
+        Ok(true) => txn.commit().await.map(|_| true),
+        Ok(false) => txn.rollback().await.map(|_| false),
+        Err(e) => {
+            if let Err(e2) = txn.rollback().await {
+                tracing::error!("Failed to rollback transaction with Error: {e2}");
+            }
+            Err(e)
+        }
+    }
+    .map_err(|e| format!("Failed to accept rejected listing: {e}"))?;
+
+    match inserted {
+        true => Ok(Review::Reviewed),
+        false => Ok(Review::Duplicate),
+    }
+}
+
+/// Marks a pending rejection as correct so that it leaves the review queue.
+pub async fn confirm(pool: &sqlx::Pool<Sqlite>, id: u32) -> Result<Review, String> {
+    sqlx::query(
+        "UPDATE rejected_listings SET status = 'confirmed', reviewed_at = ? WHERE id = ? AND status = 'pending'",
+    )
+    .bind(chrono::Utc::now())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map(|x| {
+        if x.rows_affected() > 0 {
+            Review::Reviewed
+        } else {
+            Review::NotPending
+        }
+    })
+    .map_err(|e| format!("Failed to confirm rejected listing: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    async fn pool() -> sqlx::Pool<Sqlite> {
+        // INFO: Every connection to an in-memory database gets its own, so there can only be one
+        let Ok(pool) = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+        else {
+            panic!("Failed to open database");
+        };
+        if let Err(e) = sqlx::migrate!("db/migrations").run(&pool).await {
+            panic!("Failed to migrate database: {e}");
+        }
+
+        let Ok(_) = sqlx::raw_sql(
+            "
+            INSERT INTO expansions (id, set_name, expansion_name, series_number, set_code)
+            VALUES (1, 'Scarlet & Violet', 'Temporal Forces', 5, 'TEF');
+            INSERT INTO cards (expansion_id, number, class, name, rarity)
+            VALUES (1, 1, 'Regular', 'Pikachu', 'Common'), (1, 2, 'Regular', 'Raichu', 'Common');
+            INSERT INTO rejected_listings
+                (id, source, listing_id, title, link, date, price, currency, accepts_offers, offer_was_accepted, card_expansion_id, card_number, card_class, reason, rule, rejected_at)
+            VALUES
+                (1, 'ebay_uk', 226512345678, 'Pikachu Raichu TEF', 'https://www.ebay.co.uk/itm/226512345678', '2025-01-01', 500, 'GBP', FALSE, FALSE, 1, 1, 'Regular', 'title_rule', 'proxy', '2025-01-02T00:00:00Z'),
+                (2, 'ebay_uk', 226512345678, 'Pikachu Raichu TEF', 'https://www.ebay.co.uk/itm/226512345678', '2025-01-01', 500, 'GBP', FALSE, FALSE, 1, 2, 'Regular', 'title_rule', 'proxy', '2025-01-02T00:00:00Z'),
+                (3, 'ebay_uk', 226512345678, 'Pikachu Raichu TEF', 'https://www.ebay.co.uk/itm/226512345678?_skw=raichu', '2025-01-01', 500, 'GBP', FALSE, FALSE, 1, 2, 'Regular', 'title_rule', 'proxy', '2025-01-02T00:00:00Z');
+            ",
+        )
+        .execute(&pool)
+        .await
+        else {
+            panic!("Failed to insert rejections");
+        };
+
+        pool
+    }
+
+    async fn cards_of(pool: &sqlx::Pool<Sqlite>, listing_id: i64) -> Vec<u32> {
+        let Ok(cards) = sqlx::query_as::<_, (u32,)>(
+            "SELECT card_number FROM listings_cards WHERE listing_id = ? ORDER BY card_number",
+        )
+        .bind(listing_id)
+        .fetch_all(pool)
+        .await
+        else {
+            panic!("Failed to get linked cards");
+        };
+
+        cards.into_iter().map(|(x,)| x).collect()
+    }
+
+    #[tokio::test]
+    async fn it_links_listings_already_stored_for_another_card() {
+        let pool = pool().await;
+
+        assert_eq!(accept(&pool, 1).await, Ok(Review::Reviewed));
+        assert_eq!(accept(&pool, 2).await, Ok(Review::Reviewed));
+
+        assert_eq!(cards_of(&pool, 226512345678).await, vec![1, 2]);
+        let Ok(pending) = list(&pool, "pending", 10, 0).await else {
+            panic!("Failed to list rejections");
+        };
+        assert_eq!(pending.1, 1);
+    }
+
+    #[tokio::test]
+    async fn it_leaves_listings_already_stored_for_the_card_pending() {
+        let pool = pool().await;
+
+        assert_eq!(accept(&pool, 2).await, Ok(Review::Reviewed));
+        assert_eq!(accept(&pool, 3).await, Ok(Review::Duplicate));
+
+        assert_eq!(cards_of(&pool, 226512345678).await, vec![2]);
+        assert_eq!(accept(&pool, 3).await, Ok(Review::Duplicate));
+    }
+}
@@ -0,0 +1,415 @@
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    app_state::AppState,
+    html_template::HtmlTemplate,
+    pagination::{Page, PageQuery},
+};
+use crate::{
+    card_scraper::{
+        control::{ScrapeRequest, ScraperStatus},
+        grading::{self, GradeParser},
+        schedule::{self, ScheduleSummary},
+        Expansion,
+    },
+    currency::{
+        fx::{self, FxRate},
+        Money,
+    },
+    expansion_registry::{self, RegisteredExpansion},
+    rejected_listings::{self, RejectedListing, Review},
+    scrape_failures::{self, StoredFailure},
+};
+
+pub async fn list_expansions(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<RegisteredExpansion>>, (StatusCode, String)> {
+    expansion_registry::list(&app_state.pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Registers an expansion from a manifest in the same format as the files in `expansions/`.
+pub async fn register_expansion(
+    State(app_state): State<AppState>,
+    manifest: String,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let expansion = serde_json::from_str::<Expansion>(&manifest).map_err(|e| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Invalid manifest: {e}"),
+        )
+    })?;
+
+    expansion_registry::register(&app_state.pool, &manifest, &expansion)
+        .await
+        .map(|_| StatusCode::CREATED)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+pub async fn enable_expansion(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    set_enabled(&app_state, id, true).await
+}
+
+pub async fn disable_expansion(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    set_enabled(&app_state, id, false).await
+}
+
+async fn set_enabled(
+    app_state: &AppState,
+    id: u32,
+    enabled: bool,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match expansion_registry::set_enabled(&app_state.pool, id, enabled).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Expansion {id} isn't registered"),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
+/// Re-derives the grade of every stored listing, returning how many listings changed.
+pub async fn regrade_listings(
+    State(app_state): State<AppState>,
+) -> Result<Json<u64>, (StatusCode, String)> {
+    let parser = GradeParser::load(&app_state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    grading::regrade_listings(&app_state.pool, &parser)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScraperStatusResponse {
+    #[serde(flatten)]
+    status: ScraperStatus,
+    schedule: ScheduleSummary,
+}
+
+pub async fn scraper_status(
+    State(app_state): State<AppState>,
+) -> Result<Json<ScraperStatusResponse>, (StatusCode, String)> {
+    let schedule = schedule::summary(&app_state.pool, chrono::Utc::now())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to summarise scrape schedule: {e}"),
+            )
+        })?;
+
+    Ok(Json(ScraperStatusResponse {
+        status: app_state.scraper.status(),
+        schedule,
+    }))
+}
+
+/// Pauses the scraper once the card it's scraping has been stored.
+pub async fn pause_scraper(State(app_state): State<AppState>) -> StatusCode {
+    app_state.scraper.pause();
+    StatusCode::NO_CONTENT
+}
+
+pub async fn resume_scraper(State(app_state): State<AppState>) -> StatusCode {
+    app_state.scraper.resume();
+    StatusCode::NO_CONTENT
+}
+
+/// Checks the schedule for due cards now instead of waiting for the scraper to finish sleeping.
+pub async fn trigger_scraper(State(app_state): State<AppState>) -> StatusCode {
+    app_state.scraper.trigger();
+    StatusCode::ACCEPTED
+}
+
+/// Queues a scrape of an expansion, or a single card of it, ahead of the schedule.
+///
+/// One-off scrapes don't move the card's next scheduled scrape.
+pub async fn queue_scrape(
+    State(app_state): State<AppState>,
+    Json(request): Json<ScrapeRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let expansion = expansion_registry::get(&app_state.pool, request.expansion_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Expansion {} isn't registered", request.expansion_id),
+        ))?;
+
+    if !expansion.cards.iter().any(|card| request.matches(card)) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("No cards in {} match the request", expansion.expansion_name),
+        ));
+    }
+
+    app_state.scraper.enqueue(request);
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// The most recent exchange rate of every currency pair.
+pub async fn list_fx_rates(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<FxRate>>, (StatusCode, String)> {
+    fx::list_latest(&app_state.pool)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Stores exchange rates, replacing any already stored for the same day and pair.
+pub async fn store_fx_rates(
+    State(app_state): State<AppState>,
+    Json(rates): Json<Vec<FxRate>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    for rate in &rates {
+        rate.validate()
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+
+    fx::store(&app_state.pool, &rates)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+const REVIEW_STATUSES: [&str; 3] = ["pending", "accepted", "confirmed"];
+
+#[derive(Debug, Deserialize)]
+pub struct RejectedListingsQuery {
+    status: Option<String>,
+}
+
+struct ViewRejection {
+    id: u32,
+    title: String,
+    link: String,
+    card: String,
+    reason: String,
+    date: String,
+    price: String,
+    rejected_at: String,
+    acceptable: bool,
+}
+
+impl From<RejectedListing> for ViewRejection {
+    fn from(value: RejectedListing) -> Self {
+        ViewRejection {
+            id: value.id,
+            acceptable: value.listing_id.is_some()
+                && value.date.is_some()
+                && value.price.is_some()
+                && value.currency.is_some(),
+            title: value.title,
+            link: value.link,
+            card: format!(
+                "{} {} {} ({})",
+                value.card_set_code.unwrap_or(value.card_expansion_name),
+                value.card_number,
+                value.card_name,
+                value.card_class
+            ),
+            reason: match value.rule {
+                Some(rule) => format!("{}: {rule}", value.reason),
+                None => value.reason,
+            },
+            date: value.date.map(|x| x.to_string()).unwrap_or_default(),
+            price: value
+                .price
+                .zip(value.currency)
+                .map(|(price, currency)| Money::new(price.into(), currency).to_string())
+                .or(value.raw_price)
+                .unwrap_or_default(),
+            rejected_at: value.rejected_at.format("%Y-%m-%d %H:%M").to_string(),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "rejected_listings.html")]
+struct RejectedListingsTemplate {
+    status: String,
+    /// Every review status, and whether it's the one being shown.
+    statuses: Vec<(&'static str, bool)>,
+    page: Page<ViewRejection>,
+}
+
+/// Review page of rejected listings, pending review by default.
+pub async fn list_rejected_listings(
+    Query(query): Query<RejectedListingsQuery>,
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let status = query.status.unwrap_or("pending".into());
+    if !REVIEW_STATUSES.contains(&status.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown review status {status}"),
+        ));
+    }
+
+    let (listings, total) =
+        rejected_listings::list(&app_state.pool, &status, page.limit(), page.offset())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(HtmlTemplate(RejectedListingsTemplate {
+        statuses: REVIEW_STATUSES.map(|x| (x, x == status)).to_vec(),
+        status,
+        page: Page::new(
+            listings.into_iter().map(|x| x.into()).collect(),
+            &page,
+            total,
+        ),
+    }))
+}
+
+/// Stores a rejected listing as a sale of the card it was rejected for.
+pub async fn accept_rejected_listing(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<Redirect, (StatusCode, String)> {
+    review_outcome(id, rejected_listings::accept(&app_state.pool, id).await)
+}
+
+pub async fn confirm_rejected_listing(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<Redirect, (StatusCode, String)> {
+    review_outcome(id, rejected_listings::confirm(&app_state.pool, id).await)
+}
+
+fn review_outcome(
+    id: u32,
+    review: Result<Review, String>,
+) -> Result<Redirect, (StatusCode, String)> {
+    match review {
+        Ok(Review::Reviewed) => Ok(Redirect::to("/admin/rejected_listings")),
+        Ok(Review::NotPending) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Rejected listing {id} isn't pending review"),
+        )),
+        Ok(Review::Incomplete) => Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Rejected listing {id} is missing its id, date, price or currency"),
+        )),
+        Ok(Review::Duplicate) => Err((
+            StatusCode::CONFLICT,
+            format!("Rejected listing {id} is already stored as a sale of the card"),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
+struct ViewFailure {
+    id: u32,
+    card: String,
+    attempt: u32,
+    gave_up: bool,
+    error: String,
+    failed_at: String,
+    url: String,
+    has_screenshot: bool,
+    has_page_source: bool,
+}
+
+impl From<StoredFailure> for ViewFailure {
+    fn from(value: StoredFailure) -> Self {
+        ViewFailure {
+            id: value.id,
+            card: format!(
+                "{} {} {} ({})",
+                value.card_set_code.unwrap_or(value.card_expansion_name),
+                value.card_number,
+                value.card_name,
+                value.card_class
+            ),
+            attempt: value.attempt,
+            gave_up: value.gave_up,
+            error: value.error,
+            failed_at: value.failed_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            url: value.url.unwrap_or_default(),
+            has_screenshot: value.has_screenshot.unwrap_or_default(),
+            has_page_source: value.has_page_source.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "scrape_failures.html")]
+struct ScrapeFailuresTemplate {
+    page: Page<ViewFailure>,
+}
+
+/// Failed attempts at scraping cards, newest first, with links to what was captured.
+pub async fn list_scrape_failures(
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (failures, total) = scrape_failures::list(&app_state.pool, page.limit(), page.offset())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(HtmlTemplate(ScrapeFailuresTemplate {
+        page: Page::new(
+            failures.into_iter().map(|x| x.into()).collect(),
+            &page,
+            total,
+        ),
+    }))
+}
+
+pub async fn scrape_failure_screenshot(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let file = artifact_file(&app_state, id, scrape_failures::SCREENSHOT_FILE).await?;
+    Ok(([(header::CONTENT_TYPE, "image/png")], file))
+}
+
+/// Page source of a failure, served as plain text so that the page's scripts never run.
+pub async fn scrape_failure_page_source(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let file = artifact_file(&app_state, id, scrape_failures::PAGE_SOURCE_FILE).await?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], file))
+}
+
+async fn artifact_file(
+    app_state: &AppState,
+    id: u32,
+    name: &str,
+) -> Result<Vec<u8>, (StatusCode, String)> {
+    let artifacts = scrape_failures::artifacts(&app_state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Scrape failure {id} has no artifacts"),
+        ))?;
+
+    tokio::fs::read(std::path::Path::new(&artifacts.directory).join(name))
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, format!("Failed to read {name}: {e}")))
+}
@@ -1,5 +0,0 @@
-use axum::Json;
-
-pub async fn say_hello() -> Json<&'static str> {
-    Json("Hello")
-}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{
+        rejection::{PathRejection, QueryRejection},
+        FromRequestParts,
+    },
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+
+use super::ApiError;
+
+impl From<QueryRejection> for ApiError {
+    fn from(value: QueryRejection) -> Self {
+        Self {
+            status: value.status(),
+            message: value.body_text(),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(value: PathRejection) -> Self {
+        Self {
+            status: value.status(),
+            message: value.body_text(),
+        }
+    }
+}
+
+/// [`axum::extract::Query`] that rejects malformed queries with a JSON [`ApiError`].
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+/// [`axum::extract::Path`] that rejects malformed paths with a JSON [`ApiError`].
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    use super::*;
+    use crate::routes::pagination::PageQuery;
+
+    async fn query(uri: &str) -> Result<Query<PageQuery>, ApiError> {
+        let Ok(request) = axum::http::Request::builder().uri(uri).body(()) else {
+            panic!("Failed to build request");
+        };
+        let (mut parts, _) = request.into_parts();
+
+        Query::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn it_extracts_queries() {
+        let Ok(Query(page)) = query("/cards?page=2").await else {
+            panic!("Failed to extract query");
+        };
+
+        assert_eq!(page.page(), 2);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_malformed_queries_as_json() {
+        let Err(err) = query("/cards?page=two").await else {
+            panic!("Extracted a malformed query");
+        };
+        let response = err.into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get(axum::http::header::CONTENT_TYPE),
+            Some(&axum::http::HeaderValue::from_static("application/json"))
+        );
+    }
+}
@@ -0,0 +1,670 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, QueryBuilder, Sqlite};
+
+use super::{
+    extract::{Path, Query},
+    ApiError,
+};
+use crate::{
+    currency::{fx::ExchangeRates, Currency, Money},
+    expansion_registry::RegisteredExpansion,
+    price_snapshots::{PriceBasis, PriceSnapshot},
+    routes::{
+        app_state::AppState,
+        pagination::{Page, PageQuery},
+    },
+    statistics::Summary,
+    supply,
+};
+
+const DEFAULT_PRICE_WINDOW: u32 = 30;
+
+pub fn router() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/expansions", axum::routing::get(list_expansions))
+        .route("/expansions/{id}", axum::routing::get(get_expansion))
+        .route("/cards", axum::routing::get(list_cards))
+        .route(
+            "/cards/{expansion_id}/{number}/{class}",
+            axum::routing::get(get_card),
+        )
+        .route(
+            "/cards/{expansion_id}/{number}/{class}/listings",
+            axum::routing::get(list_card_listings),
+        )
+        .route(
+            "/cards/{expansion_id}/{number}/{class}/prices",
+            axum::routing::get(get_card_prices),
+        )
+        .route(
+            "/cards/{expansion_id}/{number}/{class}/supply",
+            axum::routing::get(get_card_supply),
+        )
+        .route("/prices", axum::routing::get(get_grouped_prices))
+}
+
+pub async fn list_expansions(
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Page<RegisteredExpansion>>, ApiError> {
+    let (total,) = sqlx::query_as::<_, (u32,)>("SELECT COUNT(*) FROM expansions")
+        .fetch_one(&app_state.pool)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to count expansions: {e}")))?;
+
+    let expansions = sqlx::query_as::<_, RegisteredExpansion>(
+        "
+        SELECT id, set_name, expansion_name, CAST(series_number AS REAL) AS series_number, printed_total, release_date, set_code, enabled, registered_at
+        FROM expansions
+        ORDER BY release_date, set_name, series_number
+        LIMIT ? OFFSET ?
+        ",
+    )
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to list expansions: {e}")))?;
+
+    Ok(Json(Page::new(expansions, &page, total)))
+}
+
+pub async fn get_expansion(
+    Path(id): Path<u32>,
+    State(app_state): State<AppState>,
+) -> Result<Json<RegisteredExpansion>, ApiError> {
+    sqlx::query_as::<_, RegisteredExpansion>(
+        "
+        SELECT id, set_name, expansion_name, CAST(series_number AS REAL) AS series_number, printed_total, release_date, set_code, enabled, registered_at
+        FROM expansions
+        WHERE id = ?
+        ",
+    )
+    .bind(id)
+    .fetch_optional(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to get expansion: {e}")))?
+    .map(Json)
+    .ok_or(ApiError::not_found(format!("Expansion {id} isn't registered")))
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CardFilter {
+    /// Exact set name, i.e. "Scarlet & Violet".
+    set: Option<String>,
+    /// Set code of the expansion, i.e. "SVI".
+    set_code: Option<String>,
+    expansion_id: Option<u32>,
+    rarity: Option<String>,
+    class: Option<String>,
+    /// Case-insensitive substring of the card name.
+    name: Option<String>,
+    /// Exact illustrator, i.e. "Mitsuhiro Arita".
+    illustrator: Option<String>,
+    /// Energy type of a Pokemon, i.e. "Fire".
+    energy_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Card {
+    expansion_id: u32,
+    set_name: String,
+    expansion_name: String,
+    series_number: f32,
+    number: u32,
+    class: String,
+    name: String,
+    rarity: String,
+    image_url: Option<String>,
+    regulation_mark: Option<String>,
+    illustrator: Option<String>,
+    /// Stage of a Pokemon, i.e. "Basic" or "Stage 1".
+    stage: Option<String>,
+    energy_type: Option<String>,
+    hp: Option<u32>,
+    /// Subtype of a trainer, i.e. "Supporter".
+    trainer_type: Option<String>,
+}
+
+const CARD_COLUMNS: &str = "
+    cards.expansion_id,
+    expansions.set_name,
+    expansions.expansion_name,
+    CAST(expansions.series_number AS REAL) AS series_number,
+    cards.number,
+    cards.class,
+    cards.name,
+    cards.rarity,
+    card_details.image_url,
+    card_details.regulation_mark,
+    card_details.illustrator,
+    card_details.stage,
+    card_details.energy_type,
+    card_details.hp,
+    card_details.trainer_type
+";
+
+const CARD_TABLES: &str = "
+    cards
+    JOIN expansions ON expansions.id = cards.expansion_id
+    LEFT JOIN card_details ON card_details.expansion_id = cards.expansion_id
+    AND card_details.number = cards.number
+";
+
+fn push_card_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &CardFilter) {
+    builder.push(" WHERE TRUE");
+
+    if let Some(set) = &filter.set {
+        builder
+            .push(" AND expansions.set_name = ")
+            .push_bind(set.clone());
+    }
+    if let Some(set_code) = &filter.set_code {
+        builder
+            .push(" AND expansions.set_code = ")
+            .push_bind(set_code.clone());
+    }
+    if let Some(expansion_id) = filter.expansion_id {
+        builder
+            .push(" AND cards.expansion_id = ")
+            .push_bind(expansion_id);
+    }
+    if let Some(rarity) = &filter.rarity {
+        builder
+            .push(" AND cards.rarity = ")
+            .push_bind(rarity.clone());
+    }
+    if let Some(class) = &filter.class {
+        builder.push(" AND cards.class = ").push_bind(class.clone());
+    }
+    if let Some(name) = &filter.name {
+        builder
+            .push(" AND cards.name LIKE ")
+            .push_bind(format!("%{name}%"));
+    }
+    if let Some(illustrator) = &filter.illustrator {
+        builder
+            .push(" AND card_details.illustrator = ")
+            .push_bind(illustrator.clone());
+    }
+    if let Some(energy_type) = &filter.energy_type {
+        builder
+            .push(" AND card_details.energy_type = ")
+            .push_bind(energy_type.clone());
+    }
+}
+
+pub async fn list_cards(
+    Query(filter): Query<CardFilter>,
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Page<Card>>, ApiError> {
+    let mut count = QueryBuilder::<Sqlite>::new(format!("SELECT COUNT(*) FROM {CARD_TABLES}"));
+    push_card_filter(&mut count, &filter);
+
+    let (total,) = count
+        .build_query_as::<(u32,)>()
+        .fetch_one(&app_state.pool)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to count cards: {e}")))?;
+
+    let mut select =
+        QueryBuilder::<Sqlite>::new(format!("SELECT {CARD_COLUMNS} FROM {CARD_TABLES}"));
+    push_card_filter(&mut select, &filter);
+    select
+        .push(" ORDER BY expansions.release_date, expansions.set_name, expansions.series_number, cards.number, cards.class")
+        .push(" LIMIT ")
+        .push_bind(page.limit())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+
+    let cards = select
+        .build_query_as::<Card>()
+        .fetch_all(&app_state.pool)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to list cards: {e}")))?;
+
+    Ok(Json(Page::new(cards, &page, total)))
+}
+
+async fn find_card(
+    app_state: &AppState,
+    (expansion_id, number, class): (u32, u32, String),
+) -> Result<Card, ApiError> {
+    let filter = CardFilter {
+        expansion_id: Some(expansion_id),
+        class: Some(class.clone()),
+        ..Default::default()
+    };
+
+    let mut select =
+        QueryBuilder::<Sqlite>::new(format!("SELECT {CARD_COLUMNS} FROM {CARD_TABLES}"));
+    push_card_filter(&mut select, &filter);
+    select.push(" AND cards.number = ").push_bind(number);
+
+    select
+        .build_query_as::<Card>()
+        .fetch_optional(&app_state.pool)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get card: {e}")))?
+        .ok_or(ApiError::not_found(format!(
+            "Card {number} ({class}) doesn't exist in expansion {expansion_id}"
+        )))
+}
+
+pub async fn get_card(
+    Path(card): Path<(u32, u32, String)>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Card>, ApiError> {
+    find_card(&app_state, card).await.map(Json)
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Listing {
+    id: i64,
+    source: String,
+    title: String,
+    date: chrono::NaiveDate,
+    /// Price in minor units of `currency`.
+    price: u32,
+    currency: String,
+    /// Postage in minor units of `currency`, zero when free and missing when not specified.
+    postage: Option<u32>,
+    link: String,
+    bids: Option<u32>,
+    accepts_offers: Option<bool>,
+    offer_was_accepted: bool,
+    graded_by: Option<String>,
+    grade: Option<f32>,
+    grade_qualifier: Option<String>,
+    /// Either "single", "quantity" or "mixed".
+    lot_kind: String,
+    quantity: Option<u32>,
+    /// Price of one card in minor units of `currency`, missing for mixed lots.
+    unit_price: Option<u32>,
+    /// Price of one card including its share of postage, missing for mixed lots or when postage
+    /// wasn't specified.
+    delivered_unit_price: Option<u32>,
+}
+
+pub async fn list_card_listings(
+    Path(card): Path<(u32, u32, String)>,
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Page<Listing>>, ApiError> {
+    let card = find_card(&app_state, card).await?;
+
+    let (total,) = sqlx::query_as::<_, (u32,)>(
+        "
+        SELECT COUNT(*)
+        FROM listings_cards
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+        ",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(card.class.clone())
+    .fetch_one(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to count listings: {e}")))?;
+
+    let listings = sqlx::query_as::<_, Listing>(
+        "
+        SELECT
+            listings.id,
+            listings.source,
+            listings.title,
+            listings.date,
+            listings.price,
+            listings.currency,
+            listings.postage,
+            listings.link,
+            listings.bids,
+            listings.accepts_offers,
+            listings.offer_was_accepted,
+            grading_companies.initials AS graded_by,
+            listings.grade,
+            listings.grade_qualifier,
+            listings.lot_kind,
+            listings.quantity,
+            listings.unit_price,
+            listings.delivered_unit_price
+        FROM
+            listings_cards
+            JOIN listings ON listings.id = listings_cards.listing_id
+            LEFT JOIN grading_companies ON grading_companies.id = listings.graded_by
+        WHERE
+            listings_cards.card_expansion_id = ?
+            AND listings_cards.card_number = ?
+            AND listings_cards.card_class = ?
+        ORDER BY
+            listings.date DESC,
+            listings.id DESC
+        LIMIT ? OFFSET ?
+        ",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(card.class)
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to list listings: {e}")))?;
+
+    Ok(Json(Page::new(listings, &page, total)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceQuery {
+    /// Number of most recent listings to compute statistics from.
+    n: Option<u32>,
+    /// Whether to use item or delivered prices, item by default.
+    basis: Option<PriceBasis>,
+}
+
+/// Statistics of a sample of prices in minor units, computed after removing outliers.
+#[derive(Debug, Serialize)]
+pub struct PriceStatistics {
+    count: usize,
+    outliers: usize,
+    min: u64,
+    q1: u64,
+    median: u64,
+    q3: u64,
+    max: u64,
+    mean: u64,
+}
+
+impl From<Summary> for PriceStatistics {
+    fn from(value: Summary) -> Self {
+        Self {
+            count: value.count,
+            outliers: value.outliers,
+            min: value.min.amount(),
+            q1: value.quartiles.q1.amount(),
+            median: value.quartiles.median.amount(),
+            q3: value.quartiles.q3.amount(),
+            max: value.max.amount(),
+            mean: value.mean.amount(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CardPrices {
+    /// Number of most recent listings the statistics were computed from.
+    window: u32,
+    basis: PriceBasis,
+    /// Currency that every listing was converted to.
+    currency: &'static str,
+    raw: Option<PriceStatistics>,
+    graded: Option<PriceStatistics>,
+    /// Daily snapshots, oldest first.
+    history: Vec<PriceSnapshot>,
+}
+
+pub async fn get_card_prices(
+    Path(card): Path<(u32, u32, String)>,
+    Query(query): Query<PriceQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<CardPrices>, ApiError> {
+    let card = find_card(&app_state, card).await?;
+    let window = query.n.unwrap_or(DEFAULT_PRICE_WINDOW);
+    let basis = query.basis.unwrap_or_default();
+
+    let prices = sqlx::query_as::<_, (u32, &'static Currency, bool)>(&format!(
+        "
+        SELECT {0}, currency, graded_by IS NOT NULL
+        FROM ranked_listings
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+          AND listing_rank <= ?
+          AND {0} IS NOT NULL
+        ",
+        basis.column()
+    ))
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(card.class.clone())
+    .bind(window)
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to get prices: {e}")))?;
+    let rates = ExchangeRates::latest(&app_state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    // INFO: Listings without an exchange rate to the reporting currency are left out
+    let prices = prices
+        .into_iter()
+        .filter_map(|(price, currency, graded)| {
+            Money::new(price.into(), currency)
+                .convert(app_state.reporting_currency, &rates)
+                .ok()
+                .map(|x| (x, graded))
+        })
+        .collect::<Vec<_>>();
+
+    let summarise = |graded: bool| {
+        Summary::of(
+            prices
+                .iter()
+                .filter(|(_, x)| *x == graded)
+                .map(|(price, _)| *price),
+            &app_state.fences,
+        )
+        .map(|x| x.map(PriceStatistics::from))
+        .map_err(|e| ApiError::internal(format!("Failed to summarise prices: {e}")))
+    };
+
+    let history = sqlx::query_as::<_, PriceSnapshot>(
+        "
+        SELECT *
+        FROM price_snapshots
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+          AND basis = ?
+        ORDER BY date, graded
+        ",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(card.class)
+    .bind(basis)
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Failed to get price history: {e}")))?;
+
+    Ok(Json(CardPrices {
+        window,
+        basis,
+        currency: app_state.reporting_currency.code(),
+        raw: summarise(false)?,
+        graded: summarise(true)?,
+        history,
+    }))
+}
+
+/// Card detail that prices can be grouped by.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceGroup {
+    Stage,
+    EnergyType,
+    TrainerType,
+}
+
+impl PriceGroup {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Stage => "card_details.stage",
+            Self::EnergyType => "card_details.energy_type",
+            Self::TrainerType => "card_details.trainer_type",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GroupedPriceQuery {
+    group_by: PriceGroup,
+    /// Number of most recent listings of each card to compute statistics from.
+    n: Option<u32>,
+    /// Whether to use item or delivered prices, item by default.
+    basis: Option<PriceBasis>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupPrices {
+    /// Missing for cards without details or that the group doesn't apply to, i.e. the stage of a
+    /// trainer.
+    value: Option<String>,
+    raw: Option<PriceStatistics>,
+    graded: Option<PriceStatistics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupedPrices {
+    group_by: PriceGroup,
+    /// Number of most recent listings of each card the statistics were computed from.
+    window: u32,
+    basis: PriceBasis,
+    /// Currency that every listing was converted to.
+    currency: &'static str,
+    groups: Vec<GroupPrices>,
+}
+
+/// Statistics of the recent sales of every card matching the filter, grouped by a card detail.
+pub async fn get_grouped_prices(
+    Query(filter): Query<CardFilter>,
+    Query(query): Query<GroupedPriceQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<GroupedPrices>, ApiError> {
+    let window = query.n.unwrap_or(DEFAULT_PRICE_WINDOW);
+    let basis = query.basis.unwrap_or_default();
+
+    let mut select = QueryBuilder::<Sqlite>::new(format!(
+        "
+        SELECT {0}, ranked_listings.{1}, ranked_listings.currency, ranked_listings.graded_by IS NOT NULL
+        FROM {CARD_TABLES}
+        JOIN ranked_listings ON ranked_listings.card_expansion_id = cards.expansion_id
+        AND ranked_listings.card_number = cards.number
+        AND ranked_listings.card_class = cards.class
+        ",
+        query.group_by.column(),
+        basis.column()
+    ));
+    push_card_filter(&mut select, &filter);
+    select
+        .push(" AND ranked_listings.listing_rank <= ")
+        .push_bind(window)
+        .push(format!(
+            " AND ranked_listings.{} IS NOT NULL",
+            basis.column()
+        ));
+
+    let prices = select
+        .build_query_as::<(Option<String>, u32, &'static Currency, bool)>()
+        .fetch_all(&app_state.pool)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get prices: {e}")))?;
+    let rates = ExchangeRates::latest(&app_state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    // INFO: Listings without an exchange rate to the reporting currency are left out
+    let groups = prices.into_iter().fold(
+        std::collections::BTreeMap::<_, Vec<(Money, bool)>>::new(),
+        |mut acc, (value, price, currency, graded)| {
+            if let Ok(price) =
+                Money::new(price.into(), currency).convert(app_state.reporting_currency, &rates)
+            {
+                acc.entry(value).or_default().push((price, graded));
+            }
+            acc
+        },
+    );
+
+    let groups = groups
+        .into_iter()
+        .map(|(value, prices)| {
+            let summarise = |graded: bool| {
+                Summary::of(
+                    prices
+                        .iter()
+                        .filter(|(_, x)| *x == graded)
+                        .map(|(price, _)| *price),
+                    &app_state.fences,
+                )
+                .map(|x| x.map(PriceStatistics::from))
+                .map_err(|e| ApiError::internal(format!("Failed to summarise prices: {e}")))
+            };
+
+            Ok(GroupPrices {
+                raw: summarise(false)?,
+                graded: summarise(true)?,
+                value,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    Ok(Json(GroupedPrices {
+        group_by: query.group_by,
+        window,
+        basis,
+        currency: app_state.reporting_currency.code(),
+        groups,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SupplyQuery {
+    /// Whether to use item or delivered prices, item by default.
+    basis: Option<PriceBasis>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CardSupply {
+    basis: PriceBasis,
+    /// Currency that every ask was converted to.
+    currency: &'static str,
+    /// Active listings that haven't ended, auctions included.
+    count: u32,
+    /// Cheapest Buy It Now price in minor units of `currency`, missing when nothing is for sale at
+    /// a fixed price.
+    lowest_ask: Option<u64>,
+}
+
+pub async fn get_card_supply(
+    Path(card): Path<(u32, u32, String)>,
+    Query(query): Query<SupplyQuery>,
+    State(app_state): State<AppState>,
+) -> Result<Json<CardSupply>, ApiError> {
+    let card = find_card(&app_state, card).await?;
+    let basis = query.basis.unwrap_or_default();
+    let rates = ExchangeRates::latest(&app_state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    let supply = supply::current(
+        &app_state.pool,
+        (card.expansion_id, card.number, &card.class),
+        basis,
+        app_state.reporting_currency,
+        &rates,
+    )
+    .await
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(CardSupply {
+        basis,
+        currency: app_state.reporting_currency.code(),
+        count: supply.count,
+        lowest_ask: supply.lowest_ask.map(|x| x.amount()),
+    }))
+}
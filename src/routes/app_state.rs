@@ -1,6 +1,17 @@
+use std::sync::Arc;
+
+use metrics_exporter_prometheus::PrometheusHandle;
 use sqlx::Sqlite;
 
+use crate::{card_scraper::control::ScraperControl, currency::Currency, statistics::Fences};
+
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub pool: sqlx::Pool<Sqlite>,
+    /// Fences used to drop outlying prices before computing headline prices.
+    pub fences: Fences,
+    /// Currency that prices from every marketplace are converted to before being compared.
+    pub reporting_currency: &'static Currency,
+    pub metrics: PrometheusHandle,
+    pub scraper: Arc<ScraperControl>,
 }
@@ -0,0 +1,387 @@
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::prelude::FromRow;
+
+use super::{
+    app_state::AppState,
+    chart::{self, Sale},
+    html_template::HtmlTemplate,
+    pagination::{Page, PageQuery},
+};
+use crate::{
+    currency::{fx::ExchangeRates, Currency, Money},
+    price_snapshots::PriceBasis,
+    statistics::Summary,
+    supply::{self, Supply},
+};
+
+const DEFAULT_PRICE_WINDOW: u32 = 30;
+/// Number of most recent sales drawn on the chart.
+const CHART_SALES: u32 = 250;
+
+#[derive(Debug, Deserialize)]
+pub struct CardQuery {
+    /// Number of most recent listings to compute statistics from.
+    n: Option<u32>,
+    /// Whether to use item or delivered prices, item by default.
+    basis: Option<PriceBasis>,
+}
+
+#[derive(Debug, FromRow)]
+struct Card {
+    expansion_id: u32,
+    set_name: String,
+    expansion_name: String,
+    series_number: f32,
+    number: u32,
+    class: String,
+    name: String,
+    rarity: String,
+    image_url: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct RankedPrice {
+    listing_rank: u32,
+    date: NaiveDate,
+    price: u32,
+    currency: &'static Currency,
+    graded: bool,
+    auction: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct Listing {
+    title: String,
+    link: String,
+    date: String,
+    #[sqlx(flatten)]
+    price: Money,
+    postage: Option<u32>,
+    bids: Option<u32>,
+    offer_was_accepted: bool,
+    graded_by: Option<String>,
+    grade: Option<f32>,
+    grade_qualifier: Option<String>,
+}
+
+struct ViewListing {
+    title: String,
+    link: String,
+    date: String,
+    price: Money,
+    postage: String,
+    format: String,
+    grade: String,
+}
+
+impl From<Listing> for ViewListing {
+    fn from(value: Listing) -> Self {
+        ViewListing {
+            title: value.title,
+            link: value.link,
+            date: value.date,
+            price: value.price,
+            postage: match value.postage {
+                Some(0) => "Free".into(),
+                Some(x) => Money::new(x.into(), value.price.currency()).to_string(),
+                None => "Not specified".into(),
+            },
+            format: match (value.bids, value.offer_was_accepted) {
+                (Some(1), _) => "Auction (1 bid)".into(),
+                (Some(bids), _) => format!("Auction ({bids} bids)"),
+                (None, true) => "Best Offer accepted".into(),
+                (None, false) => "Buy It Now".into(),
+            },
+            grade: match (value.graded_by, value.grade) {
+                (Some(company), Some(grade)) => match value.grade_qualifier {
+                    Some(qualifier) => format!("{company} {grade} {qualifier}"),
+                    None => format!("{company} {grade}"),
+                },
+                (Some(company), None) => company,
+                (None, _) => "Raw".into(),
+            },
+        }
+    }
+}
+
+/// Statistics of one slice of a card's recent sales.
+struct Breakdown {
+    label: &'static str,
+    summary: Option<Summary>,
+}
+
+#[derive(Template)]
+#[template(path = "card.html")]
+struct CardTemplate {
+    card: Card,
+    window: u32,
+    basis: PriceBasis,
+    breakdowns: Vec<Breakdown>,
+    supply: Supply,
+    chart: Option<String>,
+    page: Page<ViewListing>,
+}
+
+/// Detail page of a card variant with its recent prices, a chart of its sales and its listings.
+pub async fn card(
+    Path((expansion_id, number, class)): Path<(u32, u32, String)>,
+    Query(query): Query<CardQuery>,
+    Query(page): Query<PageQuery>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let window = query.n.unwrap_or(DEFAULT_PRICE_WINDOW);
+    let basis = query.basis.unwrap_or_default();
+
+    let card = sqlx::query_as::<_, Card>(
+        "
+        SELECT
+            cards.expansion_id,
+            expansions.set_name,
+            expansions.expansion_name,
+            CAST(expansions.series_number AS REAL) AS series_number,
+            cards.number,
+            cards.class,
+            cards.name,
+            cards.rarity,
+            card_details.image_url
+        FROM
+            cards
+            JOIN expansions ON expansions.id = cards.expansion_id
+            LEFT JOIN card_details ON card_details.expansion_id = cards.expansion_id
+            AND card_details.number = cards.number
+        WHERE
+            cards.expansion_id = ?
+            AND cards.number = ?
+            AND cards.class = ?
+        ",
+    )
+    .bind(expansion_id)
+    .bind(number)
+    .bind(&class)
+    .fetch_optional(&app_state.pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get card: {e}"),
+        )
+    })?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("Card {expansion_id} {number} {class} not found"),
+    ))?;
+
+    let prices = sqlx::query_as::<_, RankedPrice>(&format!(
+        "
+        SELECT
+            listing_rank,
+            date,
+            {0} AS price,
+            currency,
+            graded_by IS NOT NULL AS graded,
+            bids IS NOT NULL AS auction
+        FROM ranked_listings
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+          AND listing_rank <= ?
+          AND {0} IS NOT NULL
+        ORDER BY listing_rank
+        ",
+        basis.column()
+    ))
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(&card.class)
+    .bind(window.max(CHART_SALES))
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get prices: {e}"),
+        )
+    })?;
+    let rates = ExchangeRates::latest(&app_state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // INFO: Listings without an exchange rate to the reporting currency are left out
+    let prices = prices
+        .into_iter()
+        .filter_map(|x| {
+            Money::new(x.price.into(), x.currency)
+                .convert(app_state.reporting_currency, &rates)
+                .ok()
+                .map(|price| (x, price))
+        })
+        .collect::<Vec<_>>();
+
+    let supply = supply::current(
+        &app_state.pool,
+        (card.expansion_id, card.number, &card.class),
+        basis,
+        app_state.reporting_currency,
+        &rates,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let summarise = |label, include: fn(&RankedPrice) -> bool| {
+        Summary::of(
+            prices
+                .iter()
+                .filter(|(x, _)| x.listing_rank <= window && include(x))
+                .map(|(_, price)| *price),
+            &app_state.fences,
+        )
+        .map(|summary| Breakdown { label, summary })
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to summarise prices: {e}"),
+            )
+        })
+    };
+    let breakdowns = vec![
+        summarise("All", |_| true)?,
+        summarise("Raw", |x| !x.graded)?,
+        summarise("Graded", |x| x.graded)?,
+        summarise("Auction", |x| x.auction)?,
+        summarise("Buy It Now", |x| !x.auction)?,
+    ];
+
+    let sales = prices
+        .iter()
+        .filter(|(x, _)| x.listing_rank <= CHART_SALES)
+        .map(|(x, price)| Sale {
+            date: x.date,
+            price: *price,
+            graded: x.graded,
+        })
+        .collect::<Vec<_>>();
+    let medians = match sales.iter().map(|x| x.date).min() {
+        Some(first) => sqlx::query_as::<_, (NaiveDate, u32)>(
+            "
+            SELECT date, median
+            FROM price_snapshots
+            WHERE card_expansion_id = ?
+              AND card_number = ?
+              AND card_class = ?
+              AND graded = FALSE
+              AND basis = ?
+              AND currency = ?
+              AND date >= ?
+            ORDER BY date
+            ",
+        )
+        .bind(card.expansion_id)
+        .bind(card.number)
+        .bind(&card.class)
+        .bind(basis)
+        .bind(app_state.reporting_currency)
+        .bind(first)
+        .fetch_all(&app_state.pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get price history: {e}"),
+            )
+        })?,
+        None => vec![],
+    };
+    let medians = medians
+        .into_iter()
+        .map(|(date, median)| {
+            (
+                date,
+                Money::new(median.into(), app_state.reporting_currency),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let (total,) = sqlx::query_as::<_, (u32,)>(
+        "
+        SELECT COUNT(*)
+        FROM listings_cards
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+        ",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(&card.class)
+    .fetch_one(&app_state.pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to count listings: {e}"),
+        )
+    })?;
+
+    let listings = sqlx::query_as::<_, Listing>(
+        "
+        SELECT
+            listings.title,
+            listings.link,
+            listings.date,
+            listings.price,
+            listings.currency,
+            listings.postage,
+            listings.bids,
+            listings.offer_was_accepted,
+            grading_companies.initials AS graded_by,
+            listings.grade,
+            listings.grade_qualifier
+        FROM
+            listings_cards
+            JOIN listings ON listings.id = listings_cards.listing_id
+            LEFT JOIN grading_companies ON grading_companies.id = listings.graded_by
+        WHERE
+            listings_cards.card_expansion_id = ?
+            AND listings_cards.card_number = ?
+            AND listings_cards.card_class = ?
+        ORDER BY
+            listings.date DESC,
+            listings.id DESC
+        LIMIT ? OFFSET ?
+        ",
+    )
+    .bind(card.expansion_id)
+    .bind(card.number)
+    .bind(&card.class)
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to list listings: {e}"),
+        )
+    })?;
+
+    Ok(HtmlTemplate(CardTemplate {
+        chart: chart::sales_chart(&sales, &medians),
+        card,
+        window,
+        basis,
+        breakdowns,
+        supply,
+        page: Page::new(
+            listings.into_iter().map(|x| x.into()).collect(),
+            &page,
+            total,
+        ),
+    }))
+}
@@ -0,0 +1,205 @@
+use chrono::NaiveDate;
+
+use crate::currency::Money;
+
+const WIDTH: u64 = 640;
+const HEIGHT: u64 = 240;
+/// Space left of the plot for price labels.
+const LEFT: u64 = 70;
+/// Space below the plot for date labels.
+const BOTTOM: u64 = 24;
+const PADDING: u64 = 8;
+
+const RAW_COLOUR: &str = "#1f77b4";
+const GRADED_COLOUR: &str = "#d62728";
+
+/// A sale plotted on the chart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sale {
+    pub date: NaiveDate,
+    pub price: Money,
+    pub graded: bool,
+}
+
+/// Maps dates and prices onto pixels, keeping prices in minor units.
+struct Scale {
+    first: NaiveDate,
+    days: u64,
+    low: u64,
+    high: u64,
+}
+
+impl Scale {
+    fn x(&self, date: NaiveDate) -> u64 {
+        let width = WIDTH - LEFT - 2 * PADDING;
+        let day = (date - self.first).num_days().max(0) as u64;
+
+        match self.days {
+            0 => LEFT + PADDING + width / 2,
+            days => LEFT + PADDING + day * width / days,
+        }
+    }
+
+    fn y(&self, price: Money) -> u64 {
+        let height = HEIGHT - BOTTOM - 2 * PADDING;
+        let price = price.amount().clamp(self.low, self.high);
+
+        match self.high - self.low {
+            0 => PADDING + height / 2,
+            range => {
+                let offset = u128::from(self.high - price) * u128::from(height) / u128::from(range);
+                PADDING + offset as u64
+            }
+        }
+    }
+}
+
+/// Draws sales over time as an SVG scatter plot, with raw and graded sales in different colours
+/// and `medians` as a line through them.
+///
+/// Every price should already be in one currency. Returns `None` when there are no sales.
+pub fn sales_chart(sales: &[Sale], medians: &[(NaiveDate, Money)]) -> Option<String> {
+    let dates = sales
+        .iter()
+        .map(|x| x.date)
+        .chain(medians.iter().map(|x| x.0));
+    let prices = sales
+        .iter()
+        .map(|x| x.price)
+        .chain(medians.iter().map(|x| x.1));
+
+    let first = sales.iter().map(|x| x.date).min()?;
+    let scale = Scale {
+        first: dates.clone().min().unwrap_or(first),
+        days: (dates.clone().max().unwrap_or(first) - dates.min().unwrap_or(first)).num_days()
+            as u64,
+        low: prices.clone().map(|x| x.amount()).min().unwrap_or(0),
+        high: prices.clone().map(|x| x.amount()).max().unwrap_or(0),
+    };
+    let currency = sales[0].price.currency();
+    let last = scale.first + chrono::Days::new(scale.days);
+    let bottom = HEIGHT - BOTTOM;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" width="{WIDTH}" height="{HEIGHT}" role="img" aria-label="Sales over time">"#
+    );
+    svg.push_str(&format!(
+        r#"<line x1="{LEFT}" y1="{bottom}" x2="{WIDTH}" y2="{bottom}" stroke="black"/><line x1="{LEFT}" y1="0" x2="{LEFT}" y2="{bottom}" stroke="black"/>"#
+    ));
+    svg.push_str(&format!(
+        r#"<text x="{0}" y="{1}" text-anchor="end" font-size="12">{2}</text><text x="{0}" y="{3}" text-anchor="end" font-size="12">{4}</text>"#,
+        LEFT - 4,
+        PADDING + 4,
+        Money::new(scale.high, currency),
+        bottom - PADDING,
+        Money::new(scale.low, currency),
+    ));
+    svg.push_str(&format!(
+        r#"<text x="{LEFT}" y="{HEIGHT}" font-size="12">{}</text><text x="{WIDTH}" y="{HEIGHT}" text-anchor="end" font-size="12">{last}</text>"#,
+        scale.first,
+    ));
+
+    if !medians.is_empty() {
+        let points = medians
+            .iter()
+            .map(|(date, price)| format!("{},{}", scale.x(*date), scale.y(*price)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg.push_str(&format!(
+            r##"<polyline points="{points}" fill="none" stroke="#555" stroke-width="2"/>"##
+        ));
+    }
+
+    for sale in sales {
+        svg.push_str(&format!(
+            r#"<circle cx="{}" cy="{}" r="3" fill="{}"><title>{} {}</title></circle>"#,
+            scale.x(sale.date),
+            scale.y(sale.price),
+            match sale.graded {
+                true => GRADED_COLOUR,
+                false => RAW_COLOUR,
+            },
+            sale.date,
+            sale.price,
+        ));
+    }
+
+    svg.push_str("</svg>");
+    Some(svg)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::currency::GBP;
+
+    fn sale(day: u32, price: u64, graded: bool) -> Sale {
+        let Some(date) = NaiveDate::from_ymd_opt(2025, 1, day) else {
+            panic!("Invalid date");
+        };
+
+        Sale {
+            date,
+            price: Money::new(price, GBP),
+            graded,
+        }
+    }
+
+    #[test]
+    fn it_draws_nothing_without_sales() {
+        assert_eq!(sales_chart(&[], &[]), None);
+    }
+
+    #[test]
+    fn it_scales_sales_into_the_plot() {
+        let Some(svg) = sales_chart(&[sale(1, 100, false), sale(11, 300, true)], &[]) else {
+            panic!("Failed to draw chart");
+        };
+
+        // The cheapest, earliest sale is at the bottom left and the dearest, latest at the top
+        // right
+        assert!(svg.contains(&format!(
+            r#"<circle cx="{}" cy="{}" r="3" fill="{RAW_COLOUR}"><title>2025-01-01 £1.00</title>"#,
+            LEFT + PADDING,
+            HEIGHT - BOTTOM - PADDING
+        )));
+        assert!(svg.contains(&format!(
+            r#"<circle cx="{}" cy="{PADDING}" r="3" fill="{GRADED_COLOUR}"><title>2025-01-11 £3.00</title>"#,
+            WIDTH - PADDING
+        )));
+        assert!(svg.contains(">£3.00</text>"));
+        assert!(svg.contains(">2025-01-11</text>"));
+    }
+
+    #[test]
+    fn it_centres_a_single_sale() {
+        let Some(svg) = sales_chart(&[sale(1, 100, false)], &[]) else {
+            panic!("Failed to draw chart");
+        };
+
+        assert!(svg.contains(&format!(
+            r#"cx="{}" cy="{}""#,
+            LEFT + PADDING + (WIDTH - LEFT - 2 * PADDING) / 2,
+            PADDING + (HEIGHT - BOTTOM - 2 * PADDING) / 2
+        )));
+    }
+
+    #[test]
+    fn it_draws_medians_as_a_line() {
+        let medians = [
+            (sale(1, 0, false).date, Money::new(100, GBP)),
+            (sale(11, 0, false).date, Money::new(300, GBP)),
+        ];
+        let Some(svg) = sales_chart(&[sale(6, 200, false)], &medians) else {
+            panic!("Failed to draw chart");
+        };
+
+        assert!(svg.contains(&format!(
+            r#"<polyline points="{},{} {},{PADDING}""#,
+            LEFT + PADDING,
+            HEIGHT - BOTTOM - PADDING,
+            WIDTH - PADDING
+        )));
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PER_PAGE: u32 = 50;
+pub const MAX_PER_PAGE: u32 = 500;
+
+/// Pagination query parameters, with pages numbered from 1.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub struct PageQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl PageQuery {
+    pub fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.per_page()
+    }
+
+    pub fn offset(&self) -> u32 {
+        (self.page() - 1).saturating_mul(self.per_page())
+    }
+}
+
+/// Response envelope for a page of results.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total_items: u32,
+    pub total_pages: u32,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, query: &PageQuery, total_items: u32) -> Self {
+        let per_page = query.per_page();
+
+        Self {
+            items,
+            page: query.page(),
+            per_page,
+            total_items,
+            total_pages: total_items.div_ceil(per_page),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn it_defaults_to_the_first_page() {
+        let query = PageQuery::default();
+
+        assert_eq!(query.page(), 1);
+        assert_eq!(query.offset(), 0);
+        assert_eq!(query.limit(), DEFAULT_PER_PAGE);
+    }
+
+    #[test]
+    fn it_clamps_out_of_range_values() {
+        let query = PageQuery {
+            page: Some(0),
+            per_page: Some(100_000),
+        };
+
+        assert_eq!(query.page(), 1);
+        assert_eq!(query.per_page(), MAX_PER_PAGE);
+    }
+
+    #[test]
+    fn it_counts_pages() {
+        let query = PageQuery {
+            page: Some(3),
+            per_page: Some(20),
+        };
+        let page = Page::new(vec![(); 5], &query, 45);
+
+        assert_eq!(query.offset(), 40);
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(Page::<()>::new(vec![], &query, 0).total_pages, 0);
+    }
+}
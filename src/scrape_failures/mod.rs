@@ -0,0 +1,162 @@
+use serde::Serialize;
+use sqlx::{prelude::FromRow, sqlite::Sqlite};
+
+/// Names of the files in a failure artifact bundle.
+pub const SCREENSHOT_FILE: &str = "screenshot.png";
+pub const PAGE_SOURCE_FILE: &str = "page.html";
+pub const SUMMARY_FILE: &str = "failure.json";
+
+/// A failed attempt at scraping one class of a card.
+#[derive(Debug)]
+pub struct ScrapeFailure<'a> {
+    pub expansion_id: u32,
+    pub number: u32,
+    pub class: &'a str,
+    /// Counts from one for the first attempt at the card in a cycle.
+    pub attempt: u32,
+    pub error: &'a str,
+    /// Whether the card was skipped for the rest of the cycle.
+    pub gave_up: bool,
+}
+
+/// Stores a failure, returning its id.
+pub async fn record(
+    pool: &sqlx::Pool<Sqlite>,
+    failure: &ScrapeFailure<'_>,
+) -> Result<u32, sqlx::Error> {
+    sqlx::query(
+        "
+        INSERT INTO scrape_failures
+            (card_expansion_id, card_number, card_class, attempt, error, gave_up, failed_at)
+        VALUES
+            (?,?,?,?,?,?,?)
+        ",
+    )
+    .bind(failure.expansion_id)
+    .bind(failure.number)
+    .bind(failure.class)
+    .bind(failure.attempt)
+    .bind(failure.error)
+    .bind(failure.gave_up)
+    .bind(chrono::Utc::now())
+    .execute(pool)
+    .await
+    .map(|x| x.last_insert_rowid() as u32)
+}
+
+/// The files saved from the browser when a card failed.
+#[derive(Debug, Clone, FromRow)]
+pub struct Artifacts {
+    pub scrape_failure_id: u32,
+    pub directory: String,
+    pub url: Option<String>,
+    pub has_screenshot: bool,
+    pub has_page_source: bool,
+}
+
+pub async fn record_artifacts(
+    pool: &sqlx::Pool<Sqlite>,
+    artifacts: &Artifacts,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "
+        INSERT INTO failure_artifacts
+            (scrape_failure_id, directory, url, has_screenshot, has_page_source, captured_at)
+        VALUES
+            (?,?,?,?,?,?)
+        ",
+    )
+    .bind(artifacts.scrape_failure_id)
+    .bind(artifacts.directory.clone())
+    .bind(artifacts.url.clone())
+    .bind(artifacts.has_screenshot)
+    .bind(artifacts.has_page_source)
+    .bind(chrono::Utc::now())
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+pub async fn artifacts(
+    pool: &sqlx::Pool<Sqlite>,
+    scrape_failure_id: u32,
+) -> Result<Option<Artifacts>, String> {
+    sqlx::query_as::<_, Artifacts>(
+        "
+        SELECT scrape_failure_id, directory, url, has_screenshot, has_page_source
+        FROM failure_artifacts
+        WHERE scrape_failure_id = ?
+        ",
+    )
+    .bind(scrape_failure_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to get failure artifacts: {e}"))
+}
+
+/// A stored failure and whatever was captured alongside it.
+#[derive(Debug, Serialize, FromRow)]
+pub struct StoredFailure {
+    pub id: u32,
+    pub card_expansion_id: u32,
+    pub card_set_code: Option<String>,
+    pub card_expansion_name: String,
+    pub card_number: u32,
+    pub card_class: String,
+    pub card_name: String,
+    pub attempt: u32,
+    pub error: String,
+    pub gave_up: bool,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+    pub url: Option<String>,
+    pub has_screenshot: Option<bool>,
+    pub has_page_source: Option<bool>,
+}
+
+/// Lists failures, newest first, alongside the total number of failures.
+pub async fn list(
+    pool: &sqlx::Pool<Sqlite>,
+    limit: u32,
+    offset: u32,
+) -> Result<(Vec<StoredFailure>, u32), String> {
+    let (total,) = sqlx::query_as::<_, (u32,)>("SELECT COUNT(*) FROM scrape_failures")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to count scrape failures: {e}"))?;
+
+    let failures = sqlx::query_as::<_, StoredFailure>(
+        "
+        SELECT
+            scrape_failures.id,
+            scrape_failures.card_expansion_id,
+            expansions.set_code AS card_set_code,
+            expansions.expansion_name AS card_expansion_name,
+            scrape_failures.card_number,
+            scrape_failures.card_class,
+            cards.name AS card_name,
+            scrape_failures.attempt,
+            scrape_failures.error,
+            scrape_failures.gave_up,
+            scrape_failures.failed_at,
+            failure_artifacts.url,
+            failure_artifacts.has_screenshot,
+            failure_artifacts.has_page_source
+        FROM
+            scrape_failures
+            JOIN cards ON cards.expansion_id = scrape_failures.card_expansion_id
+            AND cards.number = scrape_failures.card_number
+            AND cards.class = scrape_failures.card_class
+            JOIN expansions ON expansions.id = scrape_failures.card_expansion_id
+            LEFT JOIN failure_artifacts ON failure_artifacts.scrape_failure_id = scrape_failures.id
+        ORDER BY scrape_failures.failed_at DESC, scrape_failures.id DESC
+        LIMIT ? OFFSET ?
+        ",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list scrape failures: {e}"))?;
+
+    Ok((failures, total))
+}
@@ -0,0 +1,314 @@
+use crate::currency::{Money, MoneyError, Rounding};
+
+/// Multiplier used by Tukey's fences, the usual definition of an outlier.
+pub const TUKEY_MULTIPLIER: f64 = 1.5;
+
+/// Sorts a sample ascending, failing if it mixes currencies.
+pub fn sorted(values: impl IntoIterator<Item = Money>) -> Result<Vec<Money>, MoneyError> {
+    let mut values = values.into_iter().collect::<Vec<_>>();
+
+    if let Some(first) = values.first() {
+        for x in &values {
+            first.checked_cmp(x)?;
+        }
+    }
+
+    values.sort();
+    Ok(values)
+}
+
+/// Computes the `numerator / denominator`th quantile of a sorted sample, linearly interpolating
+/// between the two closest ranks and rounding half to even.
+///
+/// Returns `None` for an empty sample, a quantile outside `0..=1` or a sample of mixed
+/// currencies.
+pub fn quantile(sorted: &[Money], numerator: u64, denominator: u64) -> Option<Money> {
+    if sorted.is_empty() || denominator == 0 || numerator > denominator {
+        return None;
+    }
+
+    let rank = numerator * (sorted.len() as u64 - 1);
+    let lower = sorted[(rank / denominator) as usize];
+    let upper = sorted[rank.div_ceil(denominator) as usize];
+
+    let spread = (upper - lower).ok()?;
+    let offset = spread
+        .mul_ratio(rank % denominator, denominator, Rounding::HalfEven)
+        .ok()?;
+
+    (lower + offset).ok()
+}
+
+/// Computes the median of a sorted sample, averaging the two middle values of an even-length
+/// sample.
+pub fn median(sorted: &[Money]) -> Option<Money> {
+    quantile(sorted, 1, 2)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quartiles {
+    pub q1: Money,
+    pub median: Money,
+    pub q3: Money,
+}
+
+impl Quartiles {
+    pub fn of(sorted: &[Money]) -> Option<Self> {
+        Some(Self {
+            q1: quantile(sorted, 1, 4)?,
+            median: median(sorted)?,
+            q3: quantile(sorted, 3, 4)?,
+        })
+    }
+
+    pub fn iqr(&self) -> Money {
+        // INFO: Quartiles are ordered and share a currency, so this can't fail
+        (self.q3 - self.q1).unwrap_or(Money::zero(self.q1.currency()))
+    }
+}
+
+/// Outlier fences placed `multiplier` interquartile ranges outside of the quartiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fences {
+    pub multiplier: f64,
+}
+
+impl Default for Fences {
+    fn default() -> Self {
+        Self {
+            multiplier: TUKEY_MULTIPLIER,
+        }
+    }
+}
+
+impl std::str::FromStr for Fences {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(multiplier) if multiplier.is_finite() && multiplier >= 0.0 => {
+                Ok(Self { multiplier })
+            }
+            _ => Err(format!("Invalid outlier fence multiplier {s}")),
+        }
+    }
+}
+
+impl Fences {
+    /// Returns the inclusive lower and upper bounds of non-outlying amounts in minor units.
+    ///
+    /// The bounds only decide which prices are kept, so they're left unrounded.
+    pub fn bounds(&self, quartiles: &Quartiles) -> (f64, f64) {
+        let margin = quartiles.iqr().amount() as f64 * self.multiplier;
+        (
+            quartiles.q1.amount() as f64 - margin,
+            quartiles.q3.amount() as f64 + margin,
+        )
+    }
+
+    /// Removes the outliers of a sorted sample, keeping it sorted.
+    ///
+    /// Every value lies between its own quartiles' fences when the sample is too small to have
+    /// any spread, so this never empties a non-empty sample.
+    pub fn filter(&self, sorted: &[Money]) -> Vec<Money> {
+        let Some(quartiles) = Quartiles::of(sorted) else {
+            return vec![];
+        };
+        let (lower, upper) = self.bounds(&quartiles);
+
+        sorted
+            .iter()
+            .copied()
+            .filter(|x| (lower..=upper).contains(&(x.amount() as f64)))
+            .collect()
+    }
+}
+
+/// Summary of a sample after its outliers have been removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    /// Number of values left after removing outliers.
+    pub count: usize,
+    /// Number of values removed as outliers.
+    pub outliers: usize,
+    pub min: Money,
+    pub max: Money,
+    /// Rounded half to even.
+    pub mean: Money,
+    pub quartiles: Quartiles,
+}
+
+impl Summary {
+    /// Summarises a sample, returning `None` if it's empty and failing if it mixes currencies.
+    pub fn of(
+        values: impl IntoIterator<Item = Money>,
+        fences: &Fences,
+    ) -> Result<Option<Self>, MoneyError> {
+        let values = sorted(values)?;
+        let kept = fences.filter(&values);
+
+        let (Some(min), Some(max), Some(quartiles)) =
+            (kept.first(), kept.last(), Quartiles::of(&kept))
+        else {
+            return Ok(None);
+        };
+        let mean = kept
+            .iter()
+            .copied()
+            .sum::<Result<Money, MoneyError>>()?
+            .div_rounded(kept.len() as u64, Rounding::HalfEven)?;
+
+        Ok(Some(Self {
+            count: kept.len(),
+            outliers: values.len() - kept.len(),
+            min: *min,
+            max: *max,
+            mean,
+            quartiles,
+        }))
+    }
+
+    pub fn median(&self) -> Money {
+        self.quartiles.median
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::currency::{GBP, USD};
+
+    fn gbp(values: impl IntoIterator<Item = u64>) -> Vec<Money> {
+        values.into_iter().map(|x| Money::new(x, GBP)).collect()
+    }
+
+    fn summarise(values: impl IntoIterator<Item = u64>, fences: &Fences) -> Summary {
+        let Ok(Some(summary)) = Summary::of(gbp(values), fences) else {
+            panic!("Failed to summarise sample");
+        };
+        summary
+    }
+
+    #[test]
+    fn it_has_no_statistics_for_no_samples() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(Quartiles::of(&[]), None);
+        assert_eq!(Fences::default().filter(&[]), Vec::<Money>::new());
+        assert_eq!(Summary::of([], &Fences::default()), Ok(None));
+    }
+
+    #[test]
+    fn it_summarises_one_sample() {
+        let summary = summarise([250], &Fences::default());
+
+        assert_eq!(summary.count, 1);
+        assert_eq!(summary.median(), Money::new(250, GBP));
+        assert_eq!(
+            summary.quartiles,
+            Quartiles {
+                q1: Money::new(250, GBP),
+                median: Money::new(250, GBP),
+                q3: Money::new(250, GBP)
+            }
+        );
+    }
+
+    #[test]
+    fn it_averages_the_middle_of_two_samples() {
+        let summary = summarise([300, 100], &Fences::default());
+
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.median(), Money::new(200, GBP));
+        assert_eq!(summary.quartiles.q1, Money::new(150, GBP));
+        assert_eq!(summary.quartiles.q3, Money::new(250, GBP));
+    }
+
+    #[test]
+    fn it_takes_the_middle_of_three_samples() {
+        let Ok(values) = sorted(gbp([300, 100, 200])) else {
+            panic!("Failed to sort sample");
+        };
+
+        assert_eq!(median(&values), Some(Money::new(200, GBP)));
+        assert_eq!(quantile(&values, 1, 4), Some(Money::new(150, GBP)));
+        assert_eq!(quantile(&values, 3, 4), Some(Money::new(250, GBP)));
+        assert_eq!(Fences::default().filter(&values), values);
+    }
+
+    #[test]
+    fn it_interpolates_quantiles_to_even_minor_units() {
+        let Ok(values) = sorted(gbp([1, 2, 3, 4])) else {
+            panic!("Failed to sort sample");
+        };
+
+        // 2.5 and 1.75 round to 2, 3.25 rounds to 3
+        assert_eq!(median(&values), Some(Money::new(2, GBP)));
+        assert_eq!(quantile(&values, 1, 4), Some(Money::new(2, GBP)));
+        assert_eq!(quantile(&values, 3, 4), Some(Money::new(3, GBP)));
+        assert_eq!(quantile(&values, 0, 1), Some(Money::new(1, GBP)));
+        assert_eq!(quantile(&values, 1, 1), Some(Money::new(4, GBP)));
+        assert_eq!(quantile(&values, 3, 2), None);
+        assert_eq!(quantile(&values, 1, 0), None);
+    }
+
+    #[test]
+    fn it_rounds_the_mean_to_even_minor_units() {
+        assert_eq!(
+            summarise([1, 2], &Fences::default()).mean,
+            Money::new(2, GBP)
+        );
+        assert_eq!(
+            summarise([2, 3], &Fences::default()).mean,
+            Money::new(2, GBP)
+        );
+        assert_eq!(
+            summarise([1, 1, 2], &Fences::default()).mean,
+            Money::new(1, GBP)
+        );
+    }
+
+    #[test]
+    fn it_keeps_all_equal_samples() {
+        let summary = summarise([500; 6], &Fences::default());
+
+        assert_eq!(summary.count, 6);
+        assert_eq!(summary.outliers, 0);
+        assert_eq!(summary.median(), Money::new(500, GBP));
+        assert_eq!(summary.quartiles.iqr(), Money::zero(GBP));
+    }
+
+    #[test]
+    fn it_removes_outliers() {
+        let values = [100, 110, 120, 130, 140, 10_000];
+
+        let summary = summarise(values, &Fences::default());
+        assert_eq!(summary.count, 5);
+        assert_eq!(summary.outliers, 1);
+        assert_eq!(summary.max, Money::new(140, GBP));
+        assert_eq!(summary.median(), Money::new(120, GBP));
+
+        let summary = summarise(values, &Fences { multiplier: 1000.0 });
+        assert_eq!(summary.outliers, 0);
+    }
+
+    #[test]
+    fn it_refuses_to_summarise_mixed_currencies() {
+        let values = [Money::new(100, GBP), Money::new(100, USD)];
+
+        assert_eq!(
+            Summary::of(values, &Fences::default()),
+            Err(MoneyError::CurrencyMismatch {
+                left: "GBP",
+                right: "USD"
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_fences() {
+        assert_eq!("3".parse::<Fences>(), Ok(Fences { multiplier: 3.0 }));
+        assert!("-1".parse::<Fences>().is_err());
+        assert!("NaN".parse::<Fences>().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use sqlx::sqlite::Sqlite;
+
+use crate::{
+    currency::{fx::ExchangeRates, Currency, Money},
+    price_snapshots::PriceBasis,
+};
+
+/// What's for sale of a card variant, as of the last time its active listings were scraped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Supply {
+    /// Active listings that haven't ended, auctions included.
+    pub count: u32,
+    /// Cheapest Buy It Now price on the basis, converted to the reporting currency.
+    ///
+    /// Missing when nothing is for sale at a fixed price, or none of the prices could be
+    /// converted.
+    pub lowest_ask: Option<Money>,
+}
+
+/// Counts the active listings of a card variant and finds its lowest ask.
+pub async fn current(
+    pool: &sqlx::Pool<Sqlite>,
+    (expansion_id, number, class): (u32, u32, &str),
+    basis: PriceBasis,
+    currency: &'static Currency,
+    rates: &ExchangeRates,
+) -> Result<Supply, String> {
+    let listings = sqlx::query_as::<_, (u32, Option<u32>, &'static Currency, bool)>(
+        "
+        SELECT price, postage, currency, bids IS NOT NULL
+        FROM active_listings
+        WHERE card_expansion_id = ?
+          AND card_number = ?
+          AND card_class = ?
+          AND ended_at IS NULL
+        ",
+    )
+    .bind(expansion_id)
+    .bind(number)
+    .bind(class)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to get active listings: {e}"))?;
+
+    // INFO: An auction's price is only its current bid rather than what it can be bought for, and
+    // asks without an exchange rate to the reporting currency are left out
+    let lowest_ask = listings
+        .iter()
+        .filter(|(_, _, _, auction)| !auction)
+        .filter_map(|(price, postage, listing_currency, _)| {
+            let price = Money::new((*price).into(), listing_currency);
+            match basis {
+                PriceBasis::Item => Some(price),
+                PriceBasis::Delivered => postage.and_then(|x| {
+                    price
+                        .checked_add(Money::new(x.into(), listing_currency))
+                        .ok()
+                }),
+            }
+        })
+        .filter_map(|x| x.convert(currency, rates).ok())
+        .min_by_key(|x| x.amount());
+
+    Ok(Supply {
+        count: listings.len() as u32,
+        lowest_ask,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::currency::GBP;
+
+    async fn pool() -> sqlx::Pool<Sqlite> {
+        // INFO: Every connection to an in-memory database gets its own, so there can only be one
+        let Ok(pool) = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+        else {
+            panic!("Failed to open database");
+        };
+        if let Err(e) = sqlx::migrate!("db/migrations").run(&pool).await {
+            panic!("Failed to migrate database: {e}");
+        }
+
+        let Ok(_) = sqlx::raw_sql(
+            "
+            INSERT INTO expansions (id, set_name, expansion_name, series_number, set_code)
+            VALUES (1, 'Scarlet & Violet', 'Temporal Forces', 5, 'TEF');
+            INSERT INTO cards (expansion_id, number, class, name, rarity)
+            VALUES (1, 1, 'Regular', 'Pikachu', 'Common');
+            INSERT INTO active_listings
+                (id, source, title, price, postage, link, bids, card_expansion_id, card_number, card_class, first_seen, last_seen, ended_at)
+            VALUES
+                (1, 'ebay_uk', 'Pikachu TEF', 300, 200, 'https://www.ebay.co.uk/itm/1', NULL, 1, 1, 'Regular', '2025-01-01', '2025-01-02', NULL),
+                (2, 'ebay_uk', 'Pikachu TEF', 400, 0, 'https://www.ebay.co.uk/itm/2', NULL, 1, 1, 'Regular', '2025-01-01', '2025-01-02', NULL),
+                (3, 'ebay_uk', 'Pikachu TEF', 100, 0, 'https://www.ebay.co.uk/itm/3', 2, 1, 1, 'Regular', '2025-01-01', '2025-01-02', NULL),
+                (4, 'ebay_uk', 'Pikachu TEF', 50, 0, 'https://www.ebay.co.uk/itm/4', NULL, 1, 1, 'Regular', '2025-01-01', '2025-01-01', '2025-01-02');
+            ",
+        )
+        .execute(&pool)
+        .await
+        else {
+            panic!("Failed to insert active listings");
+        };
+
+        pool
+    }
+
+    #[tokio::test]
+    async fn it_counts_listings_that_havent_ended() {
+        let pool = pool().await;
+
+        let Ok(supply) = current(
+            &pool,
+            (1, 1, "Regular"),
+            PriceBasis::Item,
+            GBP,
+            &ExchangeRates::default(),
+        )
+        .await
+        else {
+            panic!("Failed to get supply");
+        };
+
+        assert_eq!(
+            supply,
+            Supply {
+                count: 3,
+                lowest_ask: Some(Money::new(300, GBP)),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn it_finds_the_lowest_ask_including_postage() {
+        let pool = pool().await;
+
+        let Ok(supply) = current(
+            &pool,
+            (1, 1, "Regular"),
+            PriceBasis::Delivered,
+            GBP,
+            &ExchangeRates::default(),
+        )
+        .await
+        else {
+            panic!("Failed to get supply");
+        };
+
+        assert_eq!(supply.lowest_ask, Some(Money::new(400, GBP)));
+    }
+}